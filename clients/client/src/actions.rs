@@ -1,45 +1,321 @@
 extern crate rand;
-use borsh::{BorshDeserialize, BorshSerialize};
+use crate::deck::{generate_seed, CommittedDeck};
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use solana_client::rpc_client::RpcClient;
-use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::instruction::{AccountMeta, InstructionError};
 use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signer;
 use solana_sdk::signer::keypair::Keypair;
-use solana_sdk::transaction::Transaction;
-use utils::{Error, Result};
-
-/// Sends shuffled deck of cards as an instruction from PLAYER to PROGRAM via CONNECTION.
-pub fn send_deck(player: &Keypair, program: &Keypair, connection: &RpcClient) -> Result<()> {
-    let deck = generate_deck();
-    //serialize deck
-    let mut encoded_deck: Vec<u8> = Vec::new();
-    encoded_deck.push(utils::SEND_DECK);
-    println!("Serialize deck");
-    if let Err(_) = (utils::SendDeck { deck: deck }.serialize(&mut encoded_deck)) {
-        return Err(utils::Error::Error(String::from(
-            "Deck serialization error",
+use solana_sdk::system_program;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use utils::card::{format_hand, Card, Rank, RANKS, SUITS};
+use utils::{BlackJackInstruction, Error, Result};
+
+/// Commits to a shuffled deck of cards: sends the deck's Merkle root as an instruction
+/// from PLAYER to PROGRAM via CONNECTION. Returned deck is needed to reveal the cards later.
+pub fn send_deck(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<CommittedDeck> {
+    let decks = get_account_data(table, program, connection)?.rules.decks;
+    let deck = CommittedDeck::new(generate_deck(decks));
+    println!("Send deck commitment");
+    let commitment = utils::SendDeck {
+        deck_root: deck.root(),
+        seed_commitment: deck.seed_commitment(),
+    };
+    send(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::SendDeck(commitment),
+    )?;
+    Ok(deck)
+}
+
+/// Requests a deck drawn on-chain, with randomness from the ORACLE program. Cards of this
+/// deck are drawn by the program, without reveals. Program creates the randomness account
+/// of the table at first, funded by the PLAYER.
+pub fn request_oracle_deck(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    oracle: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
+    let randomness = utils::get_randomness_address(table, &program.pubkey());
+    println!("Request oracle deck.");
+    send_with_accounts(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::RequestOracleDeck,
+        vec![
+            AccountMeta::new(randomness, false),
+            AccountMeta::new_readonly(oracle.pubkey(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Draws, from the oracle's shoe, every card the program is waiting for. Each card is drawn
+/// with its own randomness from the ORACLE program.
+pub fn draw_oracle_cards(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    oracle: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
+    let randomness = utils::get_randomness_address(table, &program.pubkey());
+    loop {
+        let account_data = get_account_data(table, program, connection)?;
+        if account_data.last_operation != utils::AWAITING_REVEAL
+            || account_data.pending_draws.is_empty()
+        {
+            return Ok(());
+        }
+        println!("Draw oracle card {}", account_data.current_card);
+        send_with_accounts(
+            player,
+            table,
+            program,
+            connection,
+            BlackJackInstruction::DrawOracleCard,
+            vec![
+                AccountMeta::new(randomness, false),
+                AccountMeta::new_readonly(oracle.pubkey(), false),
+            ],
+        )?;
+    }
+}
+
+/// Reveals, from DECK, every card the program is waiting for.
+pub fn reveal_pending_cards(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    deck: &CommittedDeck,
+) -> Result<()> {
+    loop {
+        let account_data = get_account_data(table, program, connection)?;
+        if account_data.last_operation != utils::AWAITING_REVEAL
+            || account_data.pending_draws.is_empty()
+        {
+            return Ok(());
+        }
+        let position = match account_data.next_position() {
+            Some(p) => p,
+            None => {
+                return Err(Error::Error(String::from("No cards left in the deck")));
+            }
+        };
+        let revealed = match deck.reveal(position) {
+            Some(r) => r,
+            None => {
+                return Err(Error::Error(String::from("No cards left in the deck")));
+            }
+        };
+        println!("Reveal card {}", position);
+        send(
+            player,
+            table,
+            program,
+            connection,
+            BlackJackInstruction::RevealCard(revealed),
+        )?;
+    }
+}
+
+/// Cards opened in one transaction, which fit its size limit.
+const OPEN_BATCH: usize = 24;
+
+/// Opens every card of the ended shoe from DECK, in batches in deck order, so the program
+/// checks the shoe against its commitment. Nothing is sent when the shoe of the table is not
+/// ended, is opened already, or is not DECK.
+pub fn open_shoe(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    deck: &CommittedDeck,
+) -> Result<()> {
+    let account_data = get_account_data(table, program, connection)?;
+    if !account_data.shoe_unopened() || account_data.deck_root != deck.root() {
+        return Ok(());
+    }
+    println!("Open the ended shoe");
+    let mut position = account_data.opened as usize;
+    while position < deck.card_count() {
+        let end = (position + OPEN_BATCH).min(deck.card_count());
+        let cards = (position..end).filter_map(|p| deck.open(p)).collect();
+        send(
+            player,
+            table,
+            program,
+            connection,
+            BlackJackInstruction::OpenShoe(cards),
+        )?;
+        position = end;
+    }
+    Ok(())
+}
+
+/// Commits to a fresh shuffle seed of the player. Returned seed must be revealed
+/// once the dealer reveals its own seed.
+pub fn commit_seed(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<[u8; 32]> {
+    let seed = generate_seed();
+    let commit = utils::CommitSeed {
+        seed_commitment: utils::commitment::seed_hash(&seed),
+    };
+    println!("Commit shuffle seed.");
+    send(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::PlayerCommitSeed(commit),
+    )?;
+    Ok(seed)
+}
+
+/// Reveals the shuffle SEED. OPERATION tells if it is the player's or the dealer's seed.
+pub fn reveal_seed(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    seed: [u8; 32],
+    operation: u8,
+) -> Result<()> {
+    let reveal = utils::RevealSeed { seed };
+    let instruction = if operation == utils::DEALER_REVEAL_SEED {
+        BlackJackInstruction::DealerRevealSeed(reveal)
+    } else {
+        BlackJackInstruction::PlayerRevealSeed(reveal)
+    };
+    println!("Reveal shuffle seed.");
+    send(player, table, program, connection, instruction)
+}
+
+/// Keeps the committed shoe going once the deadline of the seats has passed. The dealer
+/// reveals its SEED without the seats which did not commit, and later drops the seeds which
+/// were not revealed. Nothing is sent before the deadline.
+pub fn expire_seeds(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    seed: [u8; 32],
+) -> Result<()> {
+    let account_data = get_account_data(table, program, connection)?;
+    let waiting = account_data.phase == utils::Phase::WaitingForDeck
+        && account_data.deck_source == utils::DeckSource::Committed
+        && account_data.deck_root != [0; 32]
+        && !account_data.shuffled();
+    if !waiting || connection.get_slot()? < account_data.seed_deadline {
+        return Ok(());
+    }
+    if account_data.dealer_seed.is_none() {
+        reveal_seed(
+            dealer,
+            table,
+            program,
+            connection,
+            seed,
+            utils::DEALER_REVEAL_SEED,
+        )
+    } else {
+        println!("Drop the seeds which are not revealed.");
+        send(
+            dealer,
+            table,
+            program,
+            connection,
+            BlackJackInstruction::DropSeeds,
+        )
+    }
+}
+
+/// Checks if the deck is committed, and waits for the seed commitment of PLAYER.
+/// Every seated player shuffles the deck with the dealer, until the dealer reveals its seed.
+pub fn is_waiting_for_player_seed(
+    player: &Pubkey,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<bool> {
+    let account_data = get_account_data(table, program, connection)?;
+    Ok(account_data.phase == utils::Phase::WaitingForDeck
+        && account_data.deck_source == utils::DeckSource::Committed
+        && account_data.deck_root != [0; 32]
+        && account_data.dealer_seed.is_none()
+        && account_data
+            .seat_of(player)
+            .is_some_and(|i| account_data.seats[i].seed_commitment.is_none()))
+}
+
+/// Every seat with a bet has committed to its shuffle seed, so the dealer can reveal its own.
+pub fn all_seeds_committed(account_data: &utils::BlackJackAccountData) -> bool {
+    account_data.seeds_committed()
+}
+
+/// Fetches and decodes the blackjack TABLE. Table is an account of the PROGRAM.
+pub fn get_account_data(
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<utils::BlackJackAccountData> {
+    let account = connection.get_account(table)?;
+    if account.owner != program.pubkey() {
+        return Err(Error::Error(String::from(
+            "Table is not an account of the program",
         )));
     }
-    // println!("Serialized deck len: {}", encoded_deck.len());
-    // println!("Serialized deck: {:?}", encoded_deck);
-    send(player, program, connection, &encoded_deck)
+    utils::BlackJackAccountData::unpack(&account.data).map_err(Error::SerializationError)
 }
 
-fn send(player: &Keypair, program: &Keypair, connection: &RpcClient, data: &Vec<u8>) -> Result<()> {
-    let black_jack_account_pub_key =
-        utils::get_account_public_key(&player.pubkey(), &program.pubkey())?;
+fn send(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    instruction: BlackJackInstruction,
+) -> Result<()> {
+    send_with_accounts(player, table, program, connection, instruction, Vec::new())
+}
 
+/// Sends INSTRUCTION, signed by PLAYER, to PROGRAM together with ACCOUNTS. The blackjack
+/// TABLE is passed first, PLAYER as the signer second, and ACCOUNTS after them.
+fn send_with_accounts(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    instruction: BlackJackInstruction,
+    accounts: Vec<AccountMeta>,
+) -> Result<()> {
     // Submit an instruction to the chain which tells the program to
     // run. We pass the account that we want the results to be stored
     // in as one of the accounts arguments which the program will
-    // handle. Instruction also contains serialized deck of cards, and solana program public key.
-    let instruction = Instruction::new_with_bytes(
-        program.pubkey(),
-        &data,
-        vec![AccountMeta::new(black_jack_account_pub_key, false)],
-    );
+    // handle.
+    let instruction = instruction
+        .instruction(&program.pubkey(), table, &player.pubkey(), accounts)
+        .map_err(Error::SerializationError)?;
     let message = Message::new(&[instruction], Some(&player.pubkey()));
     let latest_hash = match connection.get_latest_blockhash() {
         Ok(hash) => hash,
@@ -51,19 +327,27 @@ fn send(player: &Keypair, program: &Keypair, connection: &RpcClient, data: &Vec<
     };
     let transaction = Transaction::new(&[player], message, latest_hash);
     println!("Send transaction");
-    connection.send_and_confirm_transaction(&transaction)?;
-    Ok(())
+    match connection.send_and_confirm_transaction(&transaction) {
+        Ok(_) => Ok(()),
+        Err(e) => match e.get_transaction_error() {
+            // Custom errors of the program are decoded into readable errors.
+            Some(TransactionError::InstructionError(_, InstructionError::Custom(code))) => {
+                Err(utils::decode_program_error(code))
+            }
+            _ => Err(e.into()),
+        },
+    }
 }
 
-/// Generate one classic deck of 52 cards and shuffle it.
+/// Generate a shoe of DECKS classic decks of 52 cards and shuffle it.
 
-fn generate_deck() -> Vec<u8> {
-    let mut result: Vec<u8> = Vec::new();
-    //four colours (spade, heart, diamond, club)
-    for _j in 0..4 {
-        for i in 1..15 {
-            if i != 11 {
-                result.push(i);
+fn generate_deck(decks: u8) -> Vec<Card> {
+    let mut result: Vec<Card> = Vec::new();
+    for _ in 0..decks {
+        //four colours (spade, heart, diamond, club)
+        for suit in SUITS {
+            for rank in RANKS {
+                result.push(Card { rank, suit });
             }
         }
     }
@@ -73,78 +357,695 @@ fn generate_deck() -> Vec<u8> {
     // println!("Generated deck: {:?}", result);
     result
 }
+/// Takes the next free seat at TABLE. PLAYER who is already seated keeps
+/// its seat. Seat taken during a round is dealt in from the next round.
+pub fn join_table(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
+    println!("Join the table.");
+    send(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::JoinTable,
+    )
+}
+
 /// Init deal operation. Dealing will be done inside the on-chain program.
-pub fn deal(player: &Keypair, program: &Keypair, connection: &RpcClient) -> Result<()> {
-    let mut data: Vec<u8> = Vec::new();
-    data.push(utils::DEAL);
+pub fn deal(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
     println!("Init dealing.");
-    send(player, program, connection, &data)
+    // house vault covers the player's bet, from its lamports or from its token account
+    let house = get_house(table, program);
+    let mut accounts = vec![AccountMeta::new(house, false)];
+    if let Some(mint) = get_account_data(table, program, connection)?.mint {
+        let house_token = get_associated_token_address(&house, &mint);
+        accounts.extend(token_accounts(table, &mint, house_token));
+    }
+    send_with_accounts(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::Deal,
+        accounts,
+    )
 }
 
 /// Init clear operation. Clearing will be done inside the on-chain program.
-pub fn clear_data(player: &Keypair, program: &Keypair, connection: &RpcClient) -> Result<()> {
-    let mut data: Vec<u8> = Vec::new();
-    data.push(utils::CLEAR_DATA);
+pub fn clear_data(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
     println!("Init clearing data.");
-    send(player, program, connection, &data)
+    send(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::ClearData,
+    )
+}
+
+/// Get init status of the PLAYER's seat: total of the hand it is playing.
+pub fn get_init_status(
+    player: &Pubkey,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<u8> {
+    let account_data = get_account_data(table, program, connection)?;
+    if let Some(card) = account_data.dealer_up_card() {
+        println!("Dealer faced up card is {}", card);
+    }
+    let seat = account_data
+        .seat_of(player)
+        .and_then(|i| account_data.seats.get(i));
+    match seat.and_then(|s| s.active()) {
+        Some(hand) => {
+            println!("Player hand is {}", format_hand(&hand.cards));
+            Ok(hand.total)
+        }
+        None => Ok(0),
+    }
+}
+
+/// Checks if the cards are dealt, and it is the turn of the PLAYER's seat.
+pub fn is_players_turn(
+    player: &Pubkey,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<bool> {
+    println!("Check if it is the player's turn");
+    let account_data = get_account_data(table, program, connection)?;
+    Ok(is_turn_of(&account_data, player))
 }
 
-/// Get init status.
-pub fn get_init_status(player: &Keypair, program: &Keypair, connection: &RpcClient) -> Result<u8> {
-    let bj_pubkey = utils::get_account_public_key(&player.pubkey(), &program.pubkey())?;
-    let account = connection.get_account(&bj_pubkey)?;
-    let account_data = utils::BlackJackAccountDataSchema::try_from_slice(&account.data)
-        .map_err(|e| Error::SerializationError(e))?;
-    println!("Dealer faced up card is {}", account_data.dealer_start2);
-    println!("Sum of initial player hand is {}", account_data.player_hand);
-    Ok(account_data.player_hand)
+/// Checks if ACCOUNT_DATA waits for the decision of the PLAYER's seat: the dealer shows
+/// an ace, or the player plays its hands, and no requested card is waiting to be revealed.
+pub fn is_turn_of(account_data: &utils::BlackJackAccountData, player: &Pubkey) -> bool {
+    (account_data.phase == utils::Phase::PlayerTurn
+        || account_data.phase == utils::Phase::Insurance)
+        && account_data.pending_draws.is_empty()
+        && account_data.seat().is_some_and(|s| s.player == *player)
 }
 
-pub fn is_deck_dealt(player: &Keypair, program: &Keypair, connection: &RpcClient) -> Result<bool> {
-    println!("Check if deck is already dealt");
-    let bj_pubkey = utils::get_account_public_key(&player.pubkey(), &program.pubkey())?;
-    let account = connection.get_account(&bj_pubkey)?;
-    let account_data = utils::BlackJackAccountDataSchema::try_from_slice(&account.data)
-        .map_err(|e| Error::SerializationError(e))?;
-    println!("Deck dealt: {}", account_data.last_operation == utils::DEAL);
-    Ok(account_data.last_operation == utils::DEAL)
+/// Number of players seated at TABLE.
+pub fn seated_players(table: &Pubkey, program: &Keypair, connection: &RpcClient) -> Result<usize> {
+    Ok(get_account_data(table, program, connection)?.seats.len())
 }
 
 /// Init hit game action. Procedure will be done on the onchain program.
 pub fn hit(
     player: &Keypair,
+    table: &Pubkey,
     program: &Keypair,
     connection: &RpcClient,
-    operation: u8,
 ) -> Result<()> {
-    let mut data: Vec<u8> = Vec::new();
-    data.push(operation);
     println!("Init hit game action.");
-    send(player, program, connection, &data)
+    send(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::PlayerHit,
+    )
 }
 
 /// Stand game action. Player ends game, and saves collected score.
 pub fn stand(
     player: &Keypair,
+    table: &Pubkey,
     program: &Keypair,
     connection: &RpcClient,
-    operation: u8,
 ) -> Result<()> {
-    let mut data: Vec<u8> = Vec::new();
-    data.push(operation);
     println!("Init stand game action.");
-    send(player, program, connection, &data)
+    send(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::PlayerStand,
+    )
 }
 
-/// Player is busted.
-pub fn busted(
+/// Doubles the bet of the PLAYER's active hand, and takes exactly one more card for it, which
+/// ends the hand. Allowed only on the first two cards, with a total allowed by the table rules.
+pub fn double(
     player: &Keypair,
+    table: &Pubkey,
     program: &Keypair,
     connection: &RpcClient,
-    operation: u8,
 ) -> Result<()> {
-    let mut data: Vec<u8> = Vec::new();
-    data.push(operation);
-    println!("Init busted game action.");
-    send(player, program, connection, &data)
+    println!("Init double game action.");
+    let mut accounts = vec![AccountMeta::new_readonly(system_program::id(), false)];
+    if let Some(mint) = get_account_data(table, program, connection)?.mint {
+        let player_token = create_token_account(player, &player.pubkey(), &mint, connection)?;
+        accounts.extend(token_accounts(table, &mint, player_token));
+    }
+    send_with_accounts(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::PlayerDouble,
+        accounts,
+    )
+}
+
+/// Dealer's up card and the most insurance the PLAYER can take, if the player decides before
+/// the dealer peeks. No insurance is taken under a ten, only the early surrender.
+pub fn get_insurance_offer(
+    player: &Pubkey,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<Option<(Card, u64)>> {
+    let account_data = get_account_data(table, program, connection)?;
+    if account_data.phase != utils::Phase::Insurance {
+        return Ok(None);
+    }
+    let up_card = match account_data.dealer_up_card() {
+        Some(card) => card,
+        None => return Ok(None),
+    };
+    let ace = up_card.rank == Rank::Ace;
+    Ok(account_data.seat_of(player).map(|i| {
+        (
+            up_card,
+            if ace {
+                account_data.seats[i].bet / 2
+            } else {
+                0
+            },
+        )
+    }))
+}
+
+/// Takes the PLAYER's insurance of AMOUNT, or EVEN_MONEY on a natural, when the dealer
+/// shows an ace. Under a ten, AMOUNT of 0 declines the early surrender. Dealer peeks at its
+/// hole card once every seat decided.
+pub fn insurance(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    amount: u64,
+    even_money: bool,
+) -> Result<()> {
+    println!(
+        "Init insurance, amount: {}, even money: {}",
+        amount, even_money
+    );
+    let mut accounts = vec![AccountMeta::new_readonly(system_program::id(), false)];
+    if let Some(mint) = get_account_data(table, program, connection)?.mint {
+        let player_token = create_token_account(player, &player.pubkey(), &mint, connection)?;
+        accounts.extend(token_accounts(table, &mint, player_token));
+    }
+    send_with_accounts(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::Insurance(utils::Insurance { amount, even_money }),
+        accounts,
+    )
+}
+
+/// PLAYER gives up its hand as the first decision, and gets back half the stake.
+pub fn surrender(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
+    println!("Init surrender game action.");
+    send(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::PlayerSurrender,
+    )
+}
+
+/// Splits the pair of the PLAYER's active hand into two hands, each with the bet of the pair.
+/// Player plays the hands in turn, each of them receiving one more card first.
+pub fn split(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
+    println!("Init split game action.");
+    let mut accounts = vec![AccountMeta::new_readonly(system_program::id(), false)];
+    if let Some(mint) = get_account_data(table, program, connection)?.mint {
+        let player_token = create_token_account(player, &player.pubkey(), &mint, connection)?;
+        accounts.extend(token_accounts(table, &mint, player_token));
+    }
+    send_with_accounts(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::PlayerSplit,
+        accounts,
+    )
+}
+
+/// Sets the house RULES of the table. Rules can be changed only between rounds, with no
+/// stake in escrow. Nothing is sent if the table has these rules already, or is not idle.
+pub fn set_rules(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    rules: utils::TableRules,
+) -> Result<()> {
+    let account_data = get_account_data(table, program, connection)?;
+    if account_data.rules == rules {
+        return Ok(());
+    }
+    if !is_table_idle(&account_data) {
+        println!("Table keeps its rules while a round is played or stakes are in escrow.");
+        return Ok(());
+    }
+    println!("Set table rules: {:?}", rules);
+    send(
+        dealer,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::SetRules(rules),
+    )
+}
+
+/// Dealer peeks at its hole card in DECK, and tells the program if it makes a natural.
+/// A natural is then revealed, and the round is settled.
+pub fn dealer_peek(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    deck: &CommittedDeck,
+) -> Result<()> {
+    let account_data = get_account_data(table, program, connection)?;
+    let hole = account_data
+        .hole_card
+        .and_then(|i| account_data.deck_order.get(i))
+        .and_then(|&p| deck.card(p as usize));
+    let natural = match (account_data.dealer_up_card(), hole) {
+        (Some(up_card), Some(hole)) => utils::card::is_natural(&[up_card, hole]),
+        _ => return Err(Error::Error(String::from("Hole card is not dealt"))),
+    };
+    println!("Dealer peeks at the hole card.");
+    send(
+        dealer,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::DealerPeek(natural),
+    )
+}
+
+/// Dealer's turn. Program plays out the dealer's hand by the table rules, and settles the round.
+/// Sent by the dealer, or by any seated player as SIGNER.
+pub fn dealer_play(
+    signer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
+    println!("Init dealer play.");
+    send(
+        signer,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::DealerPlay,
+    )
+}
+
+/// Checks if the PLAYER can bet on the next round: round is not dealt yet,
+/// and no stake of its seat is in the escrow.
+pub fn can_place_bet(
+    player: &Pubkey,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<bool> {
+    let account_data = get_account_data(table, program, connection)?;
+    let seat_empty = account_data
+        .seat_of(player)
+        .map(|i| &account_data.seats[i])
+        .is_some_and(|s| s.bet == 0 && s.dealer_stake == 0);
+    Ok(is_between_rounds(&account_data) && seat_empty)
+}
+
+/// Checks if the next round can be dealt. Round interrupted by the end of the shoe
+/// is resumed by the program with the new shoe instead.
+pub fn can_deal(table: &Pubkey, program: &Keypair, connection: &RpcClient) -> Result<bool> {
+    let account_data = get_account_data(table, program, connection)?;
+    Ok(is_between_rounds(&account_data))
+}
+
+fn is_between_rounds(account_data: &utils::BlackJackAccountData) -> bool {
+    account_data.interrupted.is_none()
+        && (account_data.phase == utils::Phase::WaitingForDeck
+            || account_data.phase == utils::Phase::Settled)
+}
+
+// Rules and mint of the table change only between rounds, with no stake in escrow.
+fn is_table_idle(account_data: &utils::BlackJackAccountData) -> bool {
+    is_between_rounds(account_data) && account_data.escrow_empty()
+}
+
+/// Moves AMOUNT lamports, or tokens of the table's mint, of the PLAYER into the escrow
+/// of the table. Program refuses the bet if the house vault can't cover its worst case.
+/// House vault covers the bet when dealing.
+pub fn place_bet(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    amount: u64,
+) -> Result<()> {
+    let house = get_house(table, program);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(house, false),
+    ];
+    match get_account_data(table, program, connection)?.mint {
+        Some(mint) => {
+            println!("Place bet of ({}) tokens of ({}).", amount, mint);
+            let player_token = create_token_account(player, &player.pubkey(), &mint, connection)?;
+            accounts.extend(token_accounts(table, &mint, player_token));
+            let house_token = get_associated_token_address(&house, &mint);
+            accounts.push(AccountMeta::new_readonly(house_token, false));
+        }
+        None => println!("Place bet of ({}) lamports.", amount),
+    }
+    send_with_accounts(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::PlaceBet(utils::PlaceBet { amount }),
+        accounts,
+    )
+}
+
+/// Takes back the bet of the PLAYER which is not dealt, from the escrow of the table.
+/// Nothing is sent if the seat has no such bet.
+pub fn withdraw_bet(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
+    let account_data = get_account_data(table, program, connection)?;
+    let undealt = account_data
+        .seat_of(&player.pubkey())
+        .map(|i| &account_data.seats[i])
+        .is_some_and(|s| s.bet > 0 && s.dealer_stake == 0);
+    if !undealt {
+        return Ok(());
+    }
+    println!("Withdraw the bet which is not dealt.");
+    let accounts = match account_data.mint {
+        Some(mint) => {
+            let player_token = get_associated_token_address(&player.pubkey(), &mint);
+            token_accounts(table, &mint, player_token)
+        }
+        None => Vec::new(),
+    };
+    send_with_accounts(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::WithdrawBet,
+        accounts,
+    )
+}
+
+/// Claims the dealt round for the players, once the dealer missed its deadline for its next
+/// step. Nothing is sent while the round doesn't wait for the dealer, or before the deadline.
+pub fn claim_forfeit(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
+    let account_data = get_account_data(table, program, connection)?;
+    if !account_data.waits_for_dealer() || connection.get_slot()? < account_data.dealer_deadline {
+        return Ok(());
+    }
+    println!("Dealer missed its deadline, claim the round.");
+    send(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::ClaimForfeit,
+    )
+}
+
+/// Pays the escrow of the settled round out to the players dealt in, and back to the house
+/// vault. Nothing is sent if the round is already paid out.
+pub fn payout(
+    player: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<()> {
+    let account_data = get_account_data(table, program, connection)?;
+    if !account_data.round_unpaid() {
+        println!("Nothing to pay out.");
+        return Ok(());
+    }
+    println!("Init payout.");
+    let house = get_house(table, program);
+    let mut accounts = vec![AccountMeta::new(house, false)];
+    match account_data.mint {
+        Some(mint) => {
+            // payout goes to the token accounts of the house and the players, created if missing
+            let house_token = create_token_account(player, &house, &mint, connection)?;
+            accounts.extend(vec![
+                AccountMeta::new(house_token, false),
+                AccountMeta::new(get_associated_token_address(table, &mint), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ]);
+            for seat in account_data.seats.iter().filter(|s| s.unpaid()) {
+                let player_token = create_token_account(player, &seat.player, &mint, connection)?;
+                accounts.push(AccountMeta::new(player_token, false));
+            }
+        }
+        None => {
+            for seat in account_data.seats.iter().filter(|s| s.unpaid()) {
+                accounts.push(AccountMeta::new(seat.player, false));
+            }
+        }
+    }
+    send_with_accounts(
+        player,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::Payout,
+        accounts,
+    )
+}
+
+/// Sets the MINT of the tokens staked at the table, or stakes lamports without a mint.
+/// Token vault of the table, and token account of the house vault, are created for the mint.
+/// Like the rules, the mint changes only when the table is idle, and only if it differs.
+pub fn set_mint(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    mint: Option<Pubkey>,
+) -> Result<()> {
+    let account_data = get_account_data(table, program, connection)?;
+    if account_data.mint == mint {
+        return Ok(());
+    }
+    if !is_table_idle(&account_data) {
+        println!("Table keeps its mint while a round is played or stakes are in escrow.");
+        return Ok(());
+    }
+    println!("Set table mint: {:?}", mint);
+    send(
+        dealer,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::SetMint(mint),
+    )?;
+    if let Some(mint) = mint {
+        let vault = create_token_account(dealer, table, &mint, connection)?;
+        println!("Token vault of the table is ({}).", vault);
+        let house = get_house(table, program);
+        create_token_account(dealer, &house, &mint, connection)?;
+    }
+    Ok(())
+}
+
+/// Mint of the tokens staked at TABLE. Lamports are staked if there is none.
+pub fn get_table_mint(
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<Option<Pubkey>> {
+    Ok(get_account_data(table, program, connection)?.mint)
+}
+
+/// House rules of the table.
+pub fn get_table_rules(
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+) -> Result<utils::TableRules> {
+    Ok(get_account_data(table, program, connection)?.rules)
+}
+
+/// Creates the associated token account of OWNER for MINT, paid by PAYER,
+/// unless it already exists. Returns the address of the token account.
+pub fn create_token_account(
+    payer: &Keypair,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    connection: &RpcClient,
+) -> Result<Pubkey> {
+    let instruction =
+        create_associated_token_account_idempotent(&payer.pubkey(), owner, mint, &spl_token::id());
+    let message = Message::new(&[instruction], Some(&payer.pubkey()));
+    let latest_hash = match connection.get_latest_blockhash() {
+        Ok(hash) => hash,
+        Err(_) => {
+            return Err(Error::LatestBlockHashError(String::from(
+                "Can't fetch latest block hash",
+            )));
+        }
+    };
+    let transaction = Transaction::new(&[payer], message, latest_hash);
+    connection.send_and_confirm_transaction(&transaction)?;
+    Ok(get_associated_token_address(owner, mint))
+}
+
+/// Tokens of MINT held by OWNER in its associated token account. Missing account holds none.
+pub fn get_token_balance(owner: &Pubkey, mint: &Pubkey, connection: &RpcClient) -> Result<u64> {
+    let address = get_associated_token_address(owner, mint);
+    if connection.get_account(&address).is_err() {
+        return Ok(0);
+    }
+    let balance = connection.get_token_account_balance(&address)?;
+    balance
+        .amount
+        .parse()
+        .map_err(|_| Error::Error(String::from("Invalid token balance")))
+}
+
+/// Token accounts which move the stake of FROM into the vault of TABLE: the staking
+/// token account, the vault, and the token program.
+fn token_accounts(table: &Pubkey, mint: &Pubkey, from: Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(from, false),
+        AccountMeta::new(get_associated_token_address(table, mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]
+}
+
+/// House vault of TABLE.
+fn get_house(table: &Pubkey, program: &Keypair) -> Pubkey {
+    utils::get_house_address(table, &program.pubkey())
+}
+
+/// Moves AMOUNT lamports, or tokens of the table's mint, of the DEALER into the house vault,
+/// which covers the bets.
+pub fn house_deposit(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    amount: u64,
+) -> Result<()> {
+    let house = get_house(table, program);
+    let mut accounts = vec![
+        AccountMeta::new(house, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if let Some(mint) = get_account_data(table, program, connection)?.mint {
+        let dealer_token = create_token_account(dealer, &dealer.pubkey(), &mint, connection)?;
+        let house_token = create_token_account(dealer, &house, &mint, connection)?;
+        accounts.extend(vec![
+            AccountMeta::new(dealer_token, false),
+            AccountMeta::new(house_token, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]);
+    }
+    println!("Deposit ({}) into the house vault.", amount);
+    send_with_accounts(
+        dealer,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::HouseDeposit(utils::HouseTransfer { amount }),
+        accounts,
+    )
+}
+
+/// Moves AMOUNT lamports, or tokens of the table's mint, from the house vault back to
+/// the DEALER. Program keeps enough in the vault to cover a bet which is not dealt yet.
+pub fn house_withdraw(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    amount: u64,
+) -> Result<()> {
+    let house = get_house(table, program);
+    let mut accounts = vec![AccountMeta::new(house, false)];
+    if let Some(mint) = get_account_data(table, program, connection)?.mint {
+        let dealer_token = create_token_account(dealer, &dealer.pubkey(), &mint, connection)?;
+        accounts.extend(vec![
+            AccountMeta::new(get_associated_token_address(&house, &mint), false),
+            AccountMeta::new(dealer_token, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]);
+    }
+    println!("Withdraw ({}) from the house vault.", amount);
+    send_with_accounts(
+        dealer,
+        table,
+        program,
+        connection,
+        BlackJackInstruction::HouseWithdraw(utils::HouseTransfer { amount }),
+        accounts,
+    )
+}
+
+/// Bankroll of the house vault of TABLE: tokens of the table's mint, or lamports
+/// above the vault's rent exemption.
+pub fn get_house_balance(table: &Pubkey, program: &Keypair, connection: &RpcClient) -> Result<u64> {
+    let house = get_house(table, program);
+    match get_account_data(table, program, connection)?.mint {
+        Some(mint) => get_token_balance(&house, &mint, connection),
+        None => {
+            let rent = connection.get_minimum_balance_for_rent_exemption(0)?;
+            Ok(connection.get_balance(&house)?.saturating_sub(rent))
+        }
+    }
 }
@@ -1,15 +1,17 @@
 use utils;
 
-use borsh::BorshDeserialize;
 use solana_account_decoder;
 use solana_account_decoder::UiAccount;
 use solana_client::pubsub_client::{AccountSubscription, PubsubClient};
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::account::Account;
 use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::AccountMeta;
 use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signer;
 use solana_sdk::signer::keypair::{read_keypair_file, Keypair};
+use solana_sdk::system_program;
 use solana_sdk::transaction::Transaction;
 use utils::{Error, Result};
 
@@ -32,20 +34,10 @@ pub fn establish_connection() -> Result<RpcClient> {
 /// Warning:
 /// Can't properly unsubscribe, see https://github.com/solana-labs/solana/issues/16102
 ///
-pub fn establish_pub_sub_connection(
-    player: &Keypair,
-    program: &Keypair,
-) -> Result<AccountSubscription> {
-    let pubkey = match utils::get_account_public_key(&player.pubkey(), &program.pubkey()) {
-        Ok(key) => key,
-        Err(_) => {
-            return Err(Error::Error(String::from(
-                "Error in getting account public key",
-            )));
-        }
-    };
+/// Subscribes to the changes of the TABLE account.
+pub fn establish_pub_sub_connection(table: &Pubkey) -> Result<AccountSubscription> {
     let ws_url = "ws://127.0.0.1:8900";
-    let pubsub_client = match PubsubClient::account_subscribe(ws_url, &pubkey, None) {
+    let pubsub_client = match PubsubClient::account_subscribe(ws_url, table, None) {
         Ok(cl) => cl,
         Err(e) => {
             println!("{:?}", e);
@@ -57,9 +49,7 @@ pub fn establish_pub_sub_connection(
     Ok(pubsub_client)
 }
 
-pub fn process_solana_network_event(
-    account: UiAccount,
-) -> Result<utils::BlackJackAccountDataSchema> {
+pub fn process_solana_network_event(account: UiAccount) -> Result<utils::BlackJackAccountData> {
     let decoded: Account = match account.decode() {
         Some(a) => a,
         None => {
@@ -69,7 +59,7 @@ pub fn process_solana_network_event(
             )));
         }
     };
-    let acc_data = match utils::BlackJackAccountDataSchema::try_from_slice(&decoded.data) {
+    let acc_data = match utils::BlackJackAccountData::unpack(&decoded.data) {
         Ok(acc) => acc,
         Err(e) => {
             println!("{:?}", e);
@@ -89,8 +79,9 @@ pub fn process_solana_network_event(
 /// For more information about rent see the Solana documentation
 /// [here](https://docs.solana.com/implemented-proposals/rent#two-tiered-rent-regime)
 pub fn get_balance_requirement(connection: &RpcClient) -> Result<u64> {
-    let account_fee =
-        connection.get_minimum_balance_for_rent_exemption(utils::get_blackjack_data_size()?)?;
+    let account_fee = connection.get_minimum_balance_for_rent_exemption(
+        utils::get_blackjack_data_size(utils::MAX_DECKS)?,
+    )?;
 
     let latest_hash = match connection.get_latest_blockhash() {
         Ok(hash) => hash,
@@ -171,42 +162,43 @@ pub fn get_program(keypair_path: &str, connection: &RpcClient) -> Result<Keypair
 /// which allows it to own and manage the account. Additionally the
 /// address being derived means that we can regenerate it when we'd
 /// like to find the  account again later.
+/// Table with INDEX of the DEALER is created by the program, sized for a shoe of DECKS.
+/// Returns the address of the table, which is left as it is if it already exists.
 pub fn create_blackjack_account(
-    player: &Keypair,
+    dealer: &Keypair,
     program: &Keypair,
     connection: &RpcClient,
-) -> Result<()> {
-    let account_pubkey = utils::get_account_public_key(&player.pubkey(), &program.pubkey())?;
-
-    if let Err(_) = connection.get_account(&account_pubkey) {
+    index: u16,
+    decks: u8,
+) -> Result<Pubkey> {
+    let account_pubkey = utils::get_table_address(&dealer.pubkey(), index, &program.pubkey());
+
+    // Lamports sent to the address beforehand don't make it a table, until the program
+    // creates the account and owns it.
+    let created = connection
+        .get_account(&account_pubkey)
+        .is_ok_and(|account| account.owner == program.pubkey());
+    if !created {
         println!("creating blackjack account");
-        let lamport_requirement =
-            connection.get_minimum_balance_for_rent_exemption(utils::get_blackjack_data_size()?)?;
-
-        // This instruction creates an account with the key
-        // "account_pubkey". The created account is owned by the
-        // program. The account is loaded with enough lamports to stop
-        // it from needing to pay rent. The lamports to fund this are
-        // paid by the player.
+
+        // The program creates the account at the address derived
+        // from the dealer and the index of the table, and signs for
+        // it. The created account is owned by the program, and the
+        // rent exemption of the account is paid by the dealer.
         //
         // It is important that the program owns the created account
-        // because it needs to be able to modify its contents.
-        //
-        // The address of the account created by
-        // create_account_with_seed is the same as the address
-        // generated by utils::get_account_public_key. We do this as
-        // opposed to create_account because create account doesn't
-        // derive that address like that.
-        let instruction = solana_sdk::system_instruction::create_account_with_seed(
-            &player.pubkey(),
-            &account_pubkey,
-            &player.pubkey(),
-            utils::get_account_seed(),
-            lamport_requirement,
-            utils::get_blackjack_data_size()? as u64,
-            &program.pubkey(),
-        );
-        let message = Message::new(&[instruction], Some(&player.pubkey()));
+        // because it needs to be able to modify its contents, and to
+        // sign for the tokens held in the vault of the table.
+        let instruction =
+            utils::BlackJackInstruction::CreateTable(utils::CreateTable { index, decks })
+                .instruction(
+                    &program.pubkey(),
+                    &account_pubkey,
+                    &dealer.pubkey(),
+                    vec![AccountMeta::new_readonly(system_program::id(), false)],
+                )
+                .map_err(Error::SerializationError)?;
+        let message = Message::new(&[instruction], Some(&dealer.pubkey()));
         let latest_hash = match connection.get_latest_blockhash() {
             Ok(hash) => hash,
             Err(_) => {
@@ -215,10 +207,10 @@ pub fn create_blackjack_account(
                 )));
             }
         };
-        let transaction = Transaction::new(&[player], message, latest_hash);
+        let transaction = Transaction::new(&[dealer], message, latest_hash);
 
         connection.send_and_confirm_transaction(&transaction)?;
     }
 
-    Ok(())
+    Ok(account_pubkey)
 }
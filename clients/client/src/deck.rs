@@ -0,0 +1,194 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use rand::thread_rng;
+use rand::RngCore;
+use solana_sdk::hash::Hash;
+use std::path::Path;
+use utils::card::Card;
+use utils::commitment::{leaf_hash, node_hash, seed_hash};
+use utils::{OpenedCard, RevealedCard};
+
+/// Random 32 bytes, used for card salts and shuffle seeds.
+pub fn generate_seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    thread_rng().fill_bytes(&mut seed);
+    seed
+}
+
+/// Shuffled deck kept by the dealer. Only the Merkle root over the salted cards
+/// is sent to the program, every card is revealed with its proof when it is dealt.
+/// Dealer's shuffle seed for the deck is kept here as well.
+pub struct CommittedDeck {
+    cards: Vec<Card>,
+    salts: Vec<[u8; 32]>,
+    layers: Vec<Vec<Hash>>, // tree levels, from the leaves up to the root
+    seed: [u8; 32],
+}
+
+impl CommittedDeck {
+    /// Salt every card of CARDS and build the Merkle tree over them.
+    pub fn new(cards: Vec<Card>) -> CommittedDeck {
+        let salts: Vec<[u8; 32]> = cards.iter().map(|_| generate_seed()).collect();
+        CommittedDeck::from_parts(cards, salts, generate_seed())
+    }
+
+    /// Deck of CARDS with their SALTS, and the dealer's shuffle SEED, with the Merkle tree
+    /// built over them.
+    fn from_parts(cards: Vec<Card>, salts: Vec<[u8; 32]>, seed: [u8; 32]) -> CommittedDeck {
+        let leaves: Vec<Hash> = cards
+            .iter()
+            .zip(salts.iter())
+            .enumerate()
+            .map(|(position, (card, salt))| leaf_hash(position, card, salt))
+            .collect();
+        let mut layers = vec![leaves];
+        while let Some(level) = layers.last().filter(|l| l.len() > 1) {
+            // A node without sibling is moved to the next level unchanged.
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+            layers.push(next);
+        }
+        CommittedDeck {
+            cards,
+            salts,
+            layers,
+            seed,
+        }
+    }
+
+    /// Stores the deck into the file at PATH. Dealer deals from the same shoe until its cut
+    /// card, so the deck must outlive the dealer application.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let stored = (self.cards.clone(), self.salts.clone(), self.seed);
+        std::fs::write(path, stored.try_to_vec()?)
+    }
+
+    /// Deck stored into the file at PATH.
+    pub fn load(path: &Path) -> std::io::Result<CommittedDeck> {
+        let (cards, salts, seed) = <(Vec<Card>, Vec<[u8; 32]>, [u8; 32])>::try_from_slice(
+            &std::fs::read(path)?,
+        )?;
+        Ok(CommittedDeck::from_parts(cards, salts, seed))
+    }
+
+    /// Dealer's shuffle seed, revealed once the player commits to its seed.
+    pub fn seed(&self) -> [u8; 32] {
+        self.seed
+    }
+
+    /// Commitment to the dealer's shuffle seed, sent together with the deck root.
+    pub fn seed_commitment(&self) -> [u8; 32] {
+        seed_hash(&self.seed)
+    }
+
+    /// Number of cards in the deck.
+    pub fn card_count(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Merkle root, the commitment sent to the program.
+    pub fn root(&self) -> [u8; 32] {
+        self.layers[self.layers.len() - 1][0].to_bytes()
+    }
+
+    /// Card on POSITION, known only to the dealer until it is revealed.
+    pub fn card(&self, position: usize) -> Option<Card> {
+        self.cards.get(position).copied()
+    }
+
+    /// Card on POSITION with its salt, opened once the shoe is ended.
+    pub fn open(&self, position: usize) -> Option<OpenedCard> {
+        Some(OpenedCard {
+            card: *self.cards.get(position)?,
+            salt: self.salts[position],
+        })
+    }
+
+    /// Card on POSITION together with the salt and proof needed by the program.
+    pub fn reveal(&self, position: usize) -> Option<RevealedCard> {
+        let card = *self.cards.get(position)?;
+        let mut proof = Vec::new();
+        let mut index = position;
+        for level in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(sibling.to_bytes());
+            }
+            index /= 2;
+        }
+        Some(RevealedCard {
+            card,
+            salt: self.salts[position],
+            proof,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::card::card_at;
+    use utils::commitment::verify_proof;
+
+    fn verify(deck: &CommittedDeck, position: usize) -> bool {
+        let revealed = deck.reveal(position).unwrap();
+        let leaf = leaf_hash(position, &revealed.card, &revealed.salt);
+        verify_proof(
+            &deck.root(),
+            leaf,
+            position,
+            deck.card_count(),
+            &revealed.proof,
+        )
+    }
+
+    #[test]
+    fn every_card_of_a_deck_verifies_against_the_root() {
+        for count in [52, 416] {
+            let deck = CommittedDeck::new((0..count).map(card_at).collect());
+            for position in 0..count {
+                assert!(verify(&deck, position), "{position} of {count}");
+            }
+        }
+    }
+
+    #[test]
+    fn node_without_sibling_is_promoted_without_proof_entry() {
+        // Levels of 52, 26, 13, 7, 4, 2 and 1 nodes: node 12 of level 2 covers cards 48..52
+        // and is moved up unchanged, as is its parent 6 of level 3, so these cards
+        // prove two levels less than the rest.
+        let deck = CommittedDeck::new((0..52).map(card_at).collect());
+        for position in 48..52 {
+            assert_eq!(deck.reveal(position).unwrap().proof.len(), 4);
+            assert!(verify(&deck, position));
+        }
+        assert_eq!(deck.reveal(0).unwrap().proof.len(), 6);
+    }
+
+    #[test]
+    fn revealed_card_does_not_verify_on_another_position_or_with_another_card() {
+        let deck = CommittedDeck::new((0..52).map(card_at).collect());
+        let revealed = deck.reveal(48).unwrap();
+        let proof = &revealed.proof;
+        let moved = leaf_hash(49, &revealed.card, &revealed.salt);
+        assert!(!verify_proof(&deck.root(), moved, 49, 52, proof));
+        let swapped = leaf_hash(48, &card_at(0), &revealed.salt);
+        assert!(!verify_proof(&deck.root(), swapped, 48, 52, proof));
+        assert!(deck.reveal(52).is_none());
+    }
+
+    #[test]
+    fn stored_deck_is_loaded_with_its_root_and_seed() {
+        let deck = CommittedDeck::new((0..52).map(card_at).collect());
+        let path = std::env::temp_dir().join(format!("deck-{}", std::process::id()));
+        deck.save(&path).unwrap();
+        let loaded = CommittedDeck::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.root(), deck.root());
+        assert_eq!(loaded.seed(), deck.seed());
+        assert_eq!(loaded.card(7), deck.card(7));
+    }
+}
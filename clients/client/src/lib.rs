@@ -1,2 +1,3 @@
 pub mod actions;
 pub mod client;
+pub mod deck;
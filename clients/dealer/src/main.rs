@@ -1,14 +1,214 @@
 use client as bj_client;
+use client::deck::CommittedDeck;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::signer::keypair::Keypair;
+use std::path::PathBuf;
 use std::process::exit;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use std_semaphore::Semaphore;
+
+/// Deals the next round once PLAYERS are seated at the TABLE.
+fn deal_when_seated(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    players: usize,
+) {
+    let seated = bj_client::actions::seated_players(table, program, connection).unwrap();
+    if seated < players {
+        println!(
+            "{} of {} players are seated, waiting for the others",
+            seated, players
+        );
+        return;
+    }
+    bj_client::actions::deal(dealer, table, program, connection).unwrap();
+    println!("Cards are dealt, waiting for players to finish");
+}
+
+/// File of the deck committed at TABLE. Shoe is dealt over many runs of the dealer, until
+/// its cut card, so its cards are revealed from the stored deck.
+fn deck_path(table: &Pubkey) -> PathBuf {
+    PathBuf::from("decks").join(table.to_string())
+}
+
+/// Starts a new deck. With the ORACLE, the deck is shuffled on-chain and the cards are dealt
+/// right away, once PLAYERS are seated. Otherwise the dealer commits to its own deck,
+/// which is shuffled together with the seated players before dealing.
+fn new_deck(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    oracle: Option<&Keypair>,
+    connection: &RpcClient,
+    deck_lock: &Mutex<Option<CommittedDeck>>,
+    players: usize,
+) {
+    // ended shoe is opened against its commitment before it is replaced
+    if let Some(deck) = deck_lock.lock().unwrap().as_ref() {
+        bj_client::actions::open_shoe(dealer, table, program, connection, deck).unwrap();
+    }
+    let account_data = bj_client::actions::get_account_data(table, program, connection).unwrap();
+    if account_data.forfeited && account_data.round_unpaid() {
+        println!("Shoe failed its check, the round must be paid out first");
+        return;
+    }
+    match oracle {
+        Some(oracle) => {
+            bj_client::actions::request_oracle_deck(dealer, table, program, oracle, connection)
+                .unwrap();
+            println!("Oracle deals a new shoe of cards");
+            // round interrupted by the end of the shoe is resumed by the program
+            if bj_client::actions::can_deal(table, program, connection).unwrap() {
+                deal_when_seated(dealer, table, program, connection, players);
+            } else {
+                println!("Round resumed with the new shoe");
+            }
+        }
+        None => {
+            let deck = bj_client::actions::send_deck(dealer, table, program, connection).unwrap();
+            std::fs::create_dir_all("decks").unwrap();
+            deck.save(&deck_path(table)).unwrap();
+            *deck_lock.lock().unwrap() = Some(deck);
+            println!("Dealer sent deck of cards, waiting for player to shuffle");
+        }
+    }
+}
+
+/// Pays the settled round out at TABLE. Ended shoe of the committed DECK is opened first,
+/// as the program checks it before the payout. A player may pay the round out first.
+fn pay_out(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    deck: Option<&CommittedDeck>,
+) {
+    if let Some(deck) = deck {
+        if let Err(e) = bj_client::actions::open_shoe(dealer, table, program, connection, deck) {
+            println!("Shoe is not opened ({})", e);
+        }
+    }
+    if let Err(e) = bj_client::actions::payout(dealer, table, program, connection) {
+        println!("Payout is not sent ({})", e);
+    }
+}
+
+/// Runs the house vault COMMAND: `deposit <amount>`, `withdraw <amount>`, or no command
+/// to inspect the bankroll. Amounts are in tokens of the table's MINT, or in lamports.
+/// Lamports for a deposit are airdropped if the dealer does not own enough of them.
+fn house_command(
+    dealer: &Keypair,
+    table: &Pubkey,
+    program: &Keypair,
+    connection: &RpcClient,
+    command: &[String],
+    mint: Option<&Pubkey>,
+) {
+    let amount = command.get(1).map(|a| a.parse::<u64>().unwrap());
+    match (command.first().map(String::as_str), amount) {
+        (Some("deposit"), Some(amount)) => {
+            if mint.is_none() {
+                let balance = bj_client::client::get_player_balance(dealer, connection).unwrap();
+                let required =
+                    amount + bj_client::client::get_balance_requirement(connection).unwrap();
+                if balance < required {
+                    println!(
+                        "Airdropping ({}) lamports for the deposit.",
+                        required - balance
+                    );
+                    bj_client::client::request_airdrop(dealer, connection, required - balance)
+                        .unwrap();
+                }
+            }
+            bj_client::actions::house_deposit(dealer, table, program, connection, amount).unwrap();
+        }
+        (Some("withdraw"), Some(amount)) => {
+            bj_client::actions::house_withdraw(dealer, table, program, connection, amount).unwrap();
+        }
+        (None, None) => {}
+        _ => {
+            eprintln!("house commands: deposit <amount>, withdraw <amount>");
+            std::process::exit(-1);
+        }
+    }
+    let bankroll = bj_client::actions::get_house_balance(table, program, connection).unwrap();
+    println!(
+        "House bankroll: ({}) {}, covers bets up to ({}).",
+        bankroll,
+        if mint.is_some() { "tokens" } else { "lamports" },
+        bankroll / utils::worst_case_payout(1).unwrap()
+    );
+}
+
+/// Balance of the DEALER in tokens of the table's MINT, or in lamports without a mint.
+fn balance(dealer: &Keypair, connection: &RpcClient, mint: Option<&Pubkey>) -> String {
+    match mint {
+        Some(mint) => format!(
+            "({}) tokens",
+            bj_client::actions::get_token_balance(&dealer.pubkey(), mint, connection).unwrap()
+        ),
+        None => format!(
+            "({}) lamports",
+            bj_client::client::get_player_balance(dealer, connection).unwrap()
+        ),
+    }
+}
+
 fn main() {
-    let args = std::env::args().collect::<Vec<_>>();
-    if args.len() != 2 {
+    let mut args = std::env::args().collect::<Vec<_>>();
+    // Dealer plays by the table rules. Stands on all 17s, unless told to hit soft 17.
+    let hit_soft_17 = args.iter().any(|arg| arg == "--hit-soft-17");
+    args.retain(|arg| arg != "--hit-soft-17");
+    // Stakes are in lamports, unless the mint of the staked tokens is given.
+    let mint = utils::flag_value(&mut args, "--mint").map(|v| Pubkey::from_str(&v).unwrap());
+    // Player doubles on any two cards, unless the totals are restricted to 9-11 or 10-11.
+    let double_on = match utils::flag_value(&mut args, "--double-on").as_deref() {
+        Some("9-11") => utils::DoubleTotals::NineToEleven,
+        Some("10-11") => utils::DoubleTotals::TenOrEleven,
+        _ => utils::DoubleTotals::Any,
+    };
+    // Player splits a pair once, unless resplitting is allowed. Split aces are played
+    // as any other hand, unless they receive one card each.
+    let resplit = args.iter().any(|arg| arg == "--resplit");
+    let split_aces_one_card = args.iter().any(|arg| arg == "--split-aces-one-card");
+    args.retain(|arg| arg != "--resplit" && arg != "--split-aces-one-card");
+    // Player can't surrender, unless late or early surrender is allowed.
+    let surrender = match utils::flag_value(&mut args, "--surrender").as_deref() {
+        Some("late") => utils::SurrenderRule::Late,
+        Some("early") => utils::SurrenderRule::Early,
+        _ => utils::SurrenderRule::Disabled,
+    };
+    // Shoe holds one deck, unless more decks are given when the table is created. Shoe is
+    // reshuffled once three quarters of it are dealt, unless the cut card position is given.
+    let decks = utils::flag_value(&mut args, "--decks").map_or(1, |v| v.parse().unwrap());
+    let cut_card = utils::flag_value(&mut args, "--cut-card").map(|v| v.parse().unwrap());
+    // Round is dealt as soon as the deck is shuffled, unless more players must be seated first.
+    let players = utils::flag_value(&mut args, "--players")
+        .map_or(1, |v| v.parse().unwrap())
+        .clamp(1, utils::MAX_SEATS);
+    // Dealer runs its table with index 0, unless the index of another of its tables is given.
+    let index = utils::flag_value(&mut args, "--table").map_or(0, |v| v.parse().unwrap());
+    // House vault is managed with `house [deposit <amount> | withdraw <amount>]`
+    // after the program keypair, instead of playing a round.
+    let house = if args.get(2).map(String::as_str) == Some("house") {
+        Some(args.split_off(3))
+    } else {
+        None
+    };
+    if args.len() != 2 && args.len() != 3 {
         eprintln!(
-            "usage: {} <path to solana hello world example program keypair>",
+            "usage: {} <path to program keypair> [path to oracle keypair | house [deposit <amount> \
+             | withdraw <amount>]] [--hit-soft-17] [--double-on <any|9-11|10-11>] \
+             [--resplit] [--split-aces-one-card] [--surrender <late|early>] \
+             [--decks <1-8>] [--cut-card <cards>] [--players <1-7>] [--mint <token mint>] \
+             [--table <index>]",
             args[0]
         );
         std::process::exit(-1);
@@ -43,9 +243,54 @@ fn main() {
     let program = bj_client::client::get_program(keypair_path, &connection).unwrap();
 
     println!("Create blackjack account");
-    bj_client::client::create_blackjack_account(&dealer, &program, &connection).unwrap();
-    let account_subscription =
-        bj_client::client::establish_pub_sub_connection(&dealer, &program).unwrap();
+    let table =
+        bj_client::client::create_blackjack_account(&dealer, &program, &connection, index, decks)
+            .unwrap();
+    println!("Table ({}) is at ({}).", index, table);
+    // House commands leave the rules and the mint of the table as they are.
+    if let Some(command) = house {
+        let table_mint = bj_client::actions::get_table_mint(&table, &program, &connection).unwrap();
+        house_command(
+            &dealer,
+            &table,
+            &program,
+            &connection,
+            &command,
+            table_mint.as_ref(),
+        );
+        exit(0);
+    }
+    let mut rules = utils::TableRules {
+        hit_soft_17,
+        double_on,
+        resplit,
+        split_aces_one_card,
+        surrender,
+        decks,
+        cut_card: 0,
+    };
+    rules.cut_card = cut_card.unwrap_or_else(|| rules.default_cut_card());
+    // table left in the middle of a round keeps its rules and mint until it is paid out
+    if let Err(e) = bj_client::actions::set_rules(&dealer, &table, &program, &connection, rules) {
+        println!("Rules are not set ({})", e);
+    }
+    if let Err(e) = bj_client::actions::set_mint(&dealer, &table, &program, &connection, mint) {
+        println!("Mint is not set ({})", e);
+    }
+    let mint = bj_client::actions::get_table_mint(&table, &program, &connection).unwrap();
+    house_command(&dealer, &table, &program, &connection, &[], mint.as_ref());
+
+    // With the oracle keypair, cards are drawn on-chain, not committed by the dealer.
+    let oracle = args
+        .get(2)
+        .map(|path| bj_client::client::get_program(path, &connection).unwrap());
+    let oracle = Arc::new(oracle);
+    let oracle1 = Arc::clone(&oracle);
+    println!(
+        "Dealer balance before the round: {}.",
+        balance(&dealer, &connection, mint.as_ref())
+    );
+    let account_subscription = bj_client::client::establish_pub_sub_connection(&table).unwrap();
 
     let receiver = account_subscription.1;
     let end_recv = Arc::new(Mutex::new(false));
@@ -57,21 +302,18 @@ fn main() {
     let program_lock1 = Arc::clone(&program_lock);
     let conn_lock = Arc::new(Mutex::new(connection));
     let conn_lock1 = Arc::clone(&conn_lock);
+    // Dealer keeps the committed deck, to reveal cards requested by the program.
+    let deck_lock = Arc::new(Mutex::new(None));
+    let deck_lock1 = Arc::clone(&deck_lock);
 
     let wait_player = Arc::new(Semaphore::new(0));
     let wait_player1 = Arc::clone(&wait_player);
 
-    let is_busted = Arc::new(Mutex::new(false));
-    let is_busted1 = Arc::clone(&is_busted);
-
-    let hit_sem = Arc::new(Semaphore::new(0));
-    let hit_sem1 = Arc::clone(&hit_sem);
-
-    let last_player_hand = Arc::new(Mutex::new(0));
-    let last_player_hand1 = Arc::clone(&last_player_hand);
+    let settled_early = Arc::new(Mutex::new(false));
+    let settled_early1 = Arc::clone(&settled_early);
 
-    let dealer_hand = Arc::new(Mutex::new(0));
-    let dealer_hand1 = Arc::clone(&dealer_hand);
+    let settled = Arc::new(Semaphore::new(0));
+    let settled1 = Arc::clone(&settled);
 
     let recv_thread = thread::spawn(move || loop {
         match receiver.recv_timeout(Duration::from_secs(2)) {
@@ -83,29 +325,169 @@ fn main() {
                     Err(_) => continue,
                 };
                 if account_data.last_operation == utils::REQUEST_NEW_DECK {
+                    if account_data.interrupted.is_some() {
+                        println!("Shoe ran out during the round, sending a new shoe");
+                    }
+                    let dealer = dealer_lock1.lock().unwrap();
+                    let program = program_lock1.lock().unwrap();
+                    let connection = conn_lock1.lock().unwrap();
+                    new_deck(
+                        &dealer,
+                        &table,
+                        &program,
+                        oracle1.as_ref().as_ref(),
+                        &connection,
+                        &deck_lock1,
+                        players,
+                    );
+                } else if account_data.last_operation == utils::PLAYER_COMMIT_SEED
+                    && bj_client::actions::all_seeds_committed(&account_data)
+                {
+                    // dealer's seed is revealed once every seated player is committed
+                    let dealer = dealer_lock1.lock().unwrap();
+                    let program = program_lock1.lock().unwrap();
+                    let connection = conn_lock1.lock().unwrap();
+                    if let Some(deck) = deck_lock1.lock().unwrap().as_ref() {
+                        bj_client::actions::reveal_seed(
+                            &dealer,
+                            &table,
+                            &program,
+                            &connection,
+                            deck.seed(),
+                            utils::DEALER_REVEAL_SEED,
+                        )
+                        .unwrap();
+                    }
+                } else if (account_data.last_operation == utils::PLAYER_REVEAL_SEED
+                    || account_data.last_operation == utils::DEALER_REVEAL_SEED
+                    || (account_data.last_operation == utils::JOIN_TABLE
+                        && account_data.phase == utils::Phase::WaitingForDeck
+                        && account_data.interrupted.is_none()))
+                    && account_data.shuffled()
+                {
+                    // shuffled deck is dealt once enough players are seated
+                    let dealer = dealer_lock1.lock().unwrap();
+                    let program = program_lock1.lock().unwrap();
+                    let connection = conn_lock1.lock().unwrap();
+                    deal_when_seated(&dealer, &table, &program, &connection, players);
+                } else if account_data.last_operation == utils::AWAITING_REVEAL {
                     let dealer = dealer_lock1.lock().unwrap();
                     let program = program_lock1.lock().unwrap();
                     let connection = conn_lock1.lock().unwrap();
-                    bj_client::actions::send_deck(&dealer, &program, &connection).unwrap();
-                    println!("Dealer dealt a new deck of cards");
-                    bj_client::actions::deal(&dealer, &program, &connection).unwrap();
-                    println!("New cards are dealt, waiting for player to finish");
-                } else if account_data.last_operation == utils::PLAYER_BUSTED {
-                    *is_busted1.lock().unwrap() = true;
+                    if let Some(deck) = deck_lock1.lock().unwrap().as_ref() {
+                        bj_client::actions::reveal_pending_cards(
+                            &dealer,
+                            &table,
+                            &program,
+                            &connection,
+                            deck,
+                        )
+                        .unwrap();
+                    } else if let Some(oracle) = oracle1.as_ref() {
+                        bj_client::actions::draw_oracle_cards(
+                            &dealer,
+                            &table,
+                            &program,
+                            oracle,
+                            &connection,
+                        )
+                        .unwrap();
+                    }
+                } else if account_data.last_operation == utils::INSURANCE {
+                    println!(
+                        "Player decides on insurance, hands: {}",
+                        utils::format_player_hands(&account_data)
+                    );
+                } else if account_data.last_operation == utils::DEALER_PEEK
+                    && account_data.phase == utils::Phase::Settled
+                {
+                    // dealer peeks at the hole card, and a revealed natural settles the round
+                    println!(
+                        "Natural blackjack, dealer hand is {}",
+                        utils::card::format_hand(&account_data.dealer_cards)
+                    );
+                } else if account_data.last_operation == utils::PLAYER_BUSTED
+                    || account_data.last_operation == utils::PLAYER_STAND
+                    || account_data.last_operation == utils::PLAYER_DOUBLE
+                    || account_data.last_operation == utils::PLAYER_SPLIT
+                    || account_data.last_operation == utils::PLAYER_SURRENDER
+                {
+                    let action = match account_data.last_operation {
+                        utils::PLAYER_BUSTED => "busts",
+                        utils::PLAYER_DOUBLE => "doubles",
+                        utils::PLAYER_SPLIT => "splits",
+                        utils::PLAYER_SURRENDER => "surrenders",
+                        _ => "stands",
+                    };
+                    println!(
+                        "Player {}, hands: {}",
+                        action,
+                        utils::format_player_hands(&account_data)
+                    );
+                } else if account_data.last_operation == utils::SHOE_FORFEIT {
+                    // shoe failed its check, or the dealer missed its deadline, and the payout
+                    // gives the players the house's cover
+                    println!("Round is forfeited to the players");
+                    *settled_early1.lock().unwrap() = true;
                     wait_player1.release();
-                } else if account_data.last_operation == utils::PLAYER_STAND {
-                    println!("Player stands with {}", account_data.player_hand);
-                    println!("Sum of dealer current hand is {}", account_data.dealer_hand);
-                    *last_player_hand1.lock().unwrap() = account_data.player_hand;
-                    *dealer_hand1.lock().unwrap() = account_data.dealer_hand;
+                    settled1.release();
+                } else if account_data.last_operation == utils::DEALER_STAND
+                    || account_data.last_operation == utils::DEALER_BUSTED
+                {
+                    println!(
+                        "Dealer hand is {}",
+                        utils::card::format_hand(&account_data.dealer_cards)
+                    );
+                    println!(
+                        "Round settled: {}",
+                        utils::format_player_hands(&account_data)
+                    );
+                    // with no hand left against the dealer's hand, the hole card is revealed
+                    // and the round settled without the dealer's play
+                    *settled_early1.lock().unwrap() = true;
                     wait_player1.release();
-                } else if account_data.last_operation == utils::DEALER_HIT {
-                    println!("Sum of current dealer hand is {}", account_data.dealer_hand);
-                    *dealer_hand1.lock().unwrap() = account_data.dealer_hand;
-                    if account_data.dealer_hand > 21 {
-                        *is_busted1.lock().unwrap() = true;
+                    settled1.release();
+                }
+                if account_data.phase == utils::Phase::Peek && account_data.pending_draws.is_empty()
+                {
+                    let dealer = dealer_lock1.lock().unwrap();
+                    let program = program_lock1.lock().unwrap();
+                    let connection = conn_lock1.lock().unwrap();
+                    if let Some(deck) = deck_lock1.lock().unwrap().as_ref() {
+                        bj_client::actions::dealer_peek(
+                            &dealer,
+                            &table,
+                            &program,
+                            &connection,
+                            deck,
+                        )
+                        .unwrap();
                     }
-                    hit_sem1.release();
+                }
+                // seats play in turn, and the dealer waits for the last one. The round is
+                // settled before the dealer plays on a natural, or when no hand is left
+                // against the dealer's hand.
+                let player_action = account_data.last_operation == utils::DEAL
+                    || account_data.last_operation == utils::INSURANCE
+                    || account_data.last_operation == utils::DEALER_PEEK
+                    || account_data.last_operation == utils::PLAYER_BUSTED
+                    || account_data.last_operation == utils::PLAYER_STAND
+                    || account_data.last_operation == utils::PLAYER_DOUBLE
+                    || account_data.last_operation == utils::PLAYER_SPLIT
+                    || account_data.last_operation == utils::PLAYER_SURRENDER;
+                if player_action && account_data.phase == utils::Phase::Settled {
+                    println!(
+                        "Round settled: {}",
+                        utils::format_player_hands(&account_data)
+                    );
+                    *settled_early1.lock().unwrap() = true;
+                    wait_player1.release();
+                } else if player_action && account_data.phase == utils::Phase::DealerTurn {
+                    println!(
+                        "Dealer current hand is {}",
+                        utils::card::format_hand(&account_data.dealer_cards)
+                    );
+                    wait_player1.release();
                 }
             }
             Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
@@ -114,6 +496,22 @@ fn main() {
                     println!("Receiver ended properly.");
                     return;
                 }
+                // seats which don't commit or reveal their seeds in time are left out of
+                // the shuffle
+                let dealer = dealer_lock1.lock().unwrap();
+                let program = program_lock1.lock().unwrap();
+                let connection = conn_lock1.lock().unwrap();
+                if let Some(deck) = deck_lock1.lock().unwrap().as_ref() {
+                    if let Err(e) = bj_client::actions::expire_seeds(
+                        &dealer,
+                        &table,
+                        &program,
+                        &connection,
+                        deck.seed(),
+                    ) {
+                        println!("Seeds are not expired ({})", e);
+                    }
+                }
             }
             Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
                 println!("Received disconnected");
@@ -126,66 +524,98 @@ fn main() {
         let dealer = dealer_lock.lock().unwrap();
         let program = program_lock.lock().unwrap();
         let connection = conn_lock.lock().unwrap();
-        println!("Send deck of cards");
-        bj_client::actions::send_deck(&dealer, &program, &connection).unwrap();
-        println!("Dealer sent deck of cards");
-
-        bj_client::actions::deal(&dealer, &program, &connection).unwrap();
-        println!("Cards are dealt, waiting for player to finish");
+        let account_data =
+            bj_client::actions::get_account_data(&table, &program, &connection).unwrap();
+        // stored deck of the committed shoe reveals its cards, and opens the shoe at its end
+        let stored = match account_data.deck_source {
+            utils::DeckSource::Committed if account_data.deck_root != [0; 32] => {
+                CommittedDeck::load(&deck_path(&table))
+                    .ok()
+                    .filter(|deck| deck.root() == account_data.deck_root)
+            }
+            _ => None,
+        };
+        if account_data.needs_shoe() {
+            *deck_lock.lock().unwrap() = stored;
+            println!("Send deck of cards");
+            new_deck(
+                &dealer,
+                &table,
+                &program,
+                oracle.as_ref().as_ref(),
+                &connection,
+                &deck_lock,
+                players,
+            );
+        } else {
+            // round is dealt from the current shoe, until its cut card
+            if account_data.deck_source == utils::DeckSource::Committed {
+                let deck = match stored {
+                    Some(deck) => deck,
+                    None => {
+                        println!("Deck of the current shoe is not stored, can't deal from it");
+                        exit(1);
+                    }
+                };
+                let seed = deck.seed();
+                *deck_lock.lock().unwrap() = Some(deck);
+                if account_data.dealer_seed.is_none()
+                    && bj_client::actions::all_seeds_committed(&account_data)
+                {
+                    bj_client::actions::reveal_seed(
+                        &dealer,
+                        &table,
+                        &program,
+                        &connection,
+                        seed,
+                        utils::DEALER_REVEAL_SEED,
+                    )
+                    .unwrap();
+                }
+            }
+            if account_data.shuffled() {
+                deal_when_seated(&dealer, &table, &program, &connection, players);
+            } else {
+                println!("Dealing from the current shoe, waiting for player to shuffle");
+            }
+        }
     }
     wait_player.acquire();
-    if *is_busted.lock().unwrap() {
-        println!("Player busted, dealer wins.");
+    if *settled_early.lock().unwrap() {
+        println!("Round settled before the dealer plays.");
         *(end_recv.lock().unwrap()) = true;
         recv_thread.join().unwrap();
         let dealer = dealer_lock.lock().unwrap();
         let program = program_lock.lock().unwrap();
         let connection = conn_lock.lock().unwrap();
-        bj_client::actions::clear_data(&dealer, &program, &connection).unwrap();
+        pay_out(&dealer, &table, &program, &connection, deck_lock.lock().unwrap().as_ref());
+        println!(
+            "Dealer balance after the round: {}.",
+            balance(&dealer, &connection, mint.as_ref())
+        );
         // must be called, because pubsubclient currently can't unsubscribe from the network.
         exit(0);
     }
 
-    println!("Delaer should hit until beats player, or go busted");
-    loop {
-        println!("Enter option:");
-        println!("1) Hit");
-        println!("2) Stand");
-        let mut line = String::new();
-        std::io::stdin().read_line(&mut line).unwrap();
-        line = line.trim().to_string();
-        if line == "1" {
-            let dealer = dealer_lock.lock().unwrap();
-            let program = program_lock.lock().unwrap();
-            let connection = conn_lock.lock().unwrap();
-            bj_client::actions::hit(&dealer, &program, &connection, utils::DEALER_HIT).unwrap();
-            hit_sem.acquire();
-            if *is_busted.lock().unwrap() {
-                println!("DEALER BUSTED");
-                //notify player and finish
-                bj_client::actions::busted(&dealer, &program, &connection, utils::DEALER_BUSTED)
-                    .unwrap();
-                break;
-            }
-        } else if line == "2" {
-            if *dealer_hand.lock().unwrap() > *last_player_hand.lock().unwrap() {
-                let dealer = dealer_lock.lock().unwrap();
-                let program = program_lock.lock().unwrap();
-                let connection = conn_lock.lock().unwrap();
-                bj_client::actions::stand(&dealer, &program, &connection, utils::DEALER_STAND)
-                    .unwrap();
-                break;
-            } else {
-                println!("Dealer should continue hitting");
-            }
-        }
+    {
+        let dealer = dealer_lock.lock().unwrap();
+        let program = program_lock.lock().unwrap();
+        let connection = conn_lock.lock().unwrap();
+        println!("Dealer plays by the table rules");
+        bj_client::actions::dealer_play(&dealer, &table, &program, &connection).unwrap();
     }
+    // receiver thread reveals the requested cards, so locks must be released here.
+    settled.acquire();
     *(end_recv.lock().unwrap()) = true;
     recv_thread.join().unwrap();
     let dealer = dealer_lock.lock().unwrap();
     let program = program_lock.lock().unwrap();
     let connection = conn_lock.lock().unwrap();
-    bj_client::actions::clear_data(&dealer, &program, &connection).unwrap();
+    pay_out(&dealer, &table, &program, &connection, deck_lock.lock().unwrap().as_ref());
+    println!(
+        "Dealer balance after the round: {}.",
+        balance(&dealer, &connection, mint.as_ref())
+    );
     // must be called, because pubsubclient currently can't unsubscribe from the network.
     exit(0);
 }
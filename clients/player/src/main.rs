@@ -1,16 +1,43 @@
 extern crate std_semaphore;
 
 use client as bj_client;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::signer::keypair::Keypair;
 use std::process::exit;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use std_semaphore::Semaphore;
 
+/// Balance of the PLAYER in tokens of the table's MINT, or in lamports without a mint.
+fn balance(player: &Keypair, connection: &RpcClient, mint: Option<&Pubkey>) -> String {
+    match mint {
+        Some(mint) => format!(
+            "({}) tokens",
+            bj_client::actions::get_token_balance(&player.pubkey(), mint, connection).unwrap()
+        ),
+        None => format!(
+            "({}) lamports",
+            bj_client::client::get_player_balance(player, connection).unwrap()
+        ),
+    }
+}
+
 fn main() {
-    let args = std::env::args().collect::<Vec<_>>();
-    if args.len() != 2 {
-        eprintln!("usage: {} <path to program keypair>", args[0]);
+    let mut args = std::env::args().collect::<Vec<_>>();
+    // Players sitting at the same table sign with their own wallets.
+    let wallet = utils::flag_value(&mut args, "--wallet");
+    // Player sits at the dealer's table with index 0, unless another index is given.
+    let index = utils::flag_value(&mut args, "--table").map_or(0, |v| v.parse::<u16>().unwrap());
+    if args.len() != 2 && args.len() != 3 {
+        eprintln!(
+            "usage: {} <path to program keypair> [dealer public key] [--wallet <path to keypair>] \
+             [--table <index>]",
+            args[0]
+        );
         std::process::exit(-1);
     }
     let keypair_path = &args[1];
@@ -27,7 +54,18 @@ fn main() {
         balance_requirement
     );
 
-    let player = utils::get_local_wallet().unwrap();
+    // Player signs with its own wallet, the player wallet unless another keypair is given.
+    // The table belongs to the dealer, which is the local wallet unless the dealer public key
+    // is given.
+    let player = match &wallet {
+        Some(path) => utils::get_wallet(path),
+        None => utils::get_player_wallet(),
+    }
+    .unwrap();
+    let dealer = match args.get(2) {
+        Some(key) => Pubkey::from_str(key).unwrap(),
+        None => utils::get_local_wallet().unwrap().pubkey(),
+    };
     let player_balance = bj_client::client::get_player_balance(&player, &connection).unwrap();
     println!("({}) lamports are owned by player.", player_balance);
 
@@ -41,112 +79,330 @@ fn main() {
     }
 
     let program = bj_client::client::get_program(keypair_path, &connection).unwrap();
+    let table = utils::get_table_address(&dealer, index, &program.pubkey());
+    println!("Table ({}) of the dealer is at ({}).", index, table);
 
-    let account_subscription =
-        bj_client::client::establish_pub_sub_connection(&player, &program).unwrap();
+    // Join before subscribing, so the receiver does not react to the joining.
+    bj_client::actions::join_table(&player, &table, &program, &connection).unwrap();
+    // Stakes are in tokens of the table's mint, or in lamports if the table has none.
+    let mint = bj_client::actions::get_table_mint(&table, &program, &connection).unwrap();
+    if bj_client::actions::can_place_bet(&player.pubkey(), &table, &program, &connection).unwrap() {
+        let unit = if mint.is_some() { "tokens" } else { "lamports" };
+        println!("Enter bet in {} (0 to play without a bet):", unit);
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).unwrap();
+        let mut bet: u64 = line.trim().parse().unwrap_or(0);
+        if let Some(mint) = &mint {
+            // tokens can't be airdropped
+            let tokens =
+                bj_client::actions::get_token_balance(&player.pubkey(), mint, &connection).unwrap();
+            if tokens < bet {
+                println!(
+                    "Player owns only ({}) tokens, playing without a bet.",
+                    tokens
+                );
+                bet = 0;
+            }
+        } else if bet > 0 {
+            let balance = bj_client::client::get_player_balance(&player, &connection).unwrap();
+            if balance < bet + balance_requirement {
+                let request = bet + balance_requirement - balance;
+                println!("Airdropping ({}) lamports for the bet.", request);
+                bj_client::client::request_airdrop(&player, &connection, request).unwrap();
+            }
+        }
+        if bet > 0 {
+            // program refuses bets which the house vault can't cover
+            if let Err(e) =
+                bj_client::actions::place_bet(&player, &table, &program, &connection, bet)
+            {
+                println!("Bet is not placed ({}), playing without a bet.", e);
+            }
+        }
+    }
+    println!(
+        "Player balance before the round: {}.",
+        balance(&player, &connection, mint.as_ref())
+    );
+    let account_subscription = bj_client::client::establish_pub_sub_connection(&table).unwrap();
 
     let receiver = account_subscription.1;
     let end_recv = Arc::new(Mutex::new(false));
     let end_recv1 = Arc::clone(&end_recv);
 
-    let deck_created = Arc::new(Semaphore::new(0));
-    let deck_created1 = Arc::clone(&deck_created);
+    // Released when the player's seat is on turn, or when the round is settled.
+    let turn = Arc::new(Semaphore::new(0));
+    let turn1 = Arc::clone(&turn);
 
-    let hit_sem = Arc::new(Semaphore::new(0));
-    let hit_sem1 = Arc::clone(&hit_sem);
+    // Phase of the round when the turn semaphore is released, which tells if the player
+    // decides on its hands, or the round is over.
+    let phase = Arc::new(Mutex::new(utils::Phase::PlayerTurn));
+    let phase1 = Arc::clone(&phase);
 
-    let busted = Arc::new(Mutex::new(false));
-    let busted1 = Arc::clone(&busted);
+    // Player's shuffle seed for the current deck, revealed after the dealer reveals its seed.
+    let seed = Arc::new(Mutex::new(None));
+    let seed1 = Arc::clone(&seed);
 
-    let dealer_finished = Arc::new(Semaphore::new(0));
-    let dealer_finished1 = Arc::clone(&dealer_finished);
+    // receiver thread takes part in shuffling of every new deck,
+    // so it needs its own keys and connection.
+    let thread_player = Keypair::from_bytes(&player.to_bytes()).unwrap();
+    let thread_program = bj_client::client::get_program(keypair_path, &connection).unwrap();
+    let thread_connection = bj_client::client::establish_connection().unwrap();
 
-    let recv_thread = thread::spawn(move || loop {
-        match receiver.recv_timeout(Duration::from_secs(2)) {
-            Ok(val) => {
-                let val = val.value;
-                // println!("Received event from solana network: {:?}", val);
-                let account_data = bj_client::client::process_solana_network_event(val).unwrap();
-                if account_data.last_operation == utils::DEAL {
-                    deck_created1.release();
-                } else if account_data.last_operation == utils::PLAYER_HIT {
-                    println!("Sum of current player hand is {}", account_data.player_hand);
-                    if account_data.player_hand > 21 {
-                        *busted1.lock().unwrap() = true;
+    let recv_thread = thread::spawn(move || {
+        // round is settled once, later events only pay it out
+        let mut settled = false;
+        loop {
+            match receiver.recv_timeout(Duration::from_secs(2)) {
+                Ok(val) => {
+                    let val = val.value;
+                    // println!("Received event from solana network: {:?}", val);
+                    let account_data =
+                        bj_client::client::process_solana_network_event(val).unwrap();
+                    let last_operation = account_data.last_operation;
+                    if last_operation == utils::SEND_DECK {
+                        println!("Dealer sent a new deck, commit shuffle seed");
+                        // every seated player shuffles the deck with the dealer
+                        let player_seed = match bj_client::actions::commit_seed(
+                            &thread_player,
+                            &table,
+                            &thread_program,
+                            &thread_connection,
+                        ) {
+                            Ok(player_seed) => Some(player_seed),
+                            Err(e) => {
+                                println!("Shuffle seed is not committed ({})", e);
+                                None
+                            }
+                        };
+                        *seed1.lock().unwrap() = player_seed;
+                    } else if last_operation == utils::DEALER_REVEAL_SEED {
+                        if let Some(player_seed) = *seed1.lock().unwrap() {
+                            bj_client::actions::reveal_seed(
+                                &thread_player,
+                                &table,
+                                &thread_program,
+                                &thread_connection,
+                                player_seed,
+                                utils::PLAYER_REVEAL_SEED,
+                            )
+                            .unwrap();
+                        }
+                    } else if last_operation == utils::REQUEST_NEW_DECK
+                        && account_data.interrupted.is_some()
+                    {
+                        // dealer sends a new shoe, and the program resumes the round with it
+                        println!("Shoe ran out, round waits for a new shoe");
+                    } else if last_operation == utils::JOIN_TABLE {
+                        println!(
+                            "Player joined the table, {} seats are taken",
+                            account_data.seats.len()
+                        );
+                    } else if last_operation == utils::DEAL
+                        || last_operation == utils::PLAYER_HIT
+                        || last_operation == utils::PLAYER_STAND
+                        || last_operation == utils::PLAYER_DOUBLE
+                        || last_operation == utils::PLAYER_SPLIT
+                        || last_operation == utils::PLAYER_BUSTED
+                        || last_operation == utils::INSURANCE
+                        || last_operation == utils::DEALER_PEEK
+                        || last_operation == utils::PLAYER_SURRENDER
+                    {
+                        println!(
+                            "Current player hands are {}",
+                            utils::format_player_hands(&account_data)
+                        );
+                        // seats take their turns, and the dealer plays after the last one
+                        if bj_client::actions::is_turn_of(&account_data, &thread_player.pubkey()) {
+                            *phase1.lock().unwrap() = account_data.phase;
+                            turn1.release();
+                        } else if account_data.phase == utils::Phase::Settled && !settled {
+                            // program settles the round on a dealer's natural, or when
+                            // no hand is left against the dealer's hand
+                            println!(
+                                "Round settled: {}",
+                                utils::format_player_hands(&account_data)
+                            );
+                            settled = true;
+                            *phase1.lock().unwrap() = utils::Phase::Settled;
+                            turn1.release();
+                        } else if account_data.phase == utils::Phase::DealerTurn {
+                            println!("Wait dealer to finish");
+                        }
+                    } else if last_operation == utils::SHOE_FORFEIT && !settled {
+                        // dealer's shoe failed its check, or the dealer missed its deadline,
+                        // and the house's cover is paid out
+                        println!("Round is forfeited to the players");
+                        settled = true;
+                        *phase1.lock().unwrap() = utils::Phase::Settled;
+                        turn1.release();
+                    } else if last_operation == utils::DEALER_BUSTED
+                        || last_operation == utils::DEALER_STAND
+                    {
+                        println!(
+                            "Dealer hand is {}",
+                            utils::card::format_hand(&account_data.dealer_cards)
+                        );
+                        println!(
+                            "Round settled: {}",
+                            utils::format_player_hands(&account_data)
+                        );
+                        if !settled {
+                            settled = true;
+                            *phase1.lock().unwrap() = utils::Phase::Settled;
+                            turn1.release();
+                        }
                     }
-                    hit_sem1.release();
-                } else if account_data.last_operation == utils::DEALER_BUSTED {
-                    println!("Dealer busted,player wins");
-                    dealer_finished1.release();
-                } else if account_data.last_operation == utils::DEALER_STAND {
-                    println!("Dealer wins");
-                    dealer_finished1.release();
                 }
-            }
-            Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
-                let should_finish = end_recv1.lock().unwrap();
-                if *should_finish {
-                    println!("Receiver ended properly.");
+                Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                    let should_finish = end_recv1.lock().unwrap();
+                    if *should_finish {
+                        println!("Receiver ended properly.");
+                        return;
+                    }
+                    // dealer which stalls the round loses it to the players
+                    if let Err(e) = bj_client::actions::claim_forfeit(
+                        &thread_player,
+                        &table,
+                        &thread_program,
+                        &thread_connection,
+                    ) {
+                        println!("Round is not claimed ({})", e);
+                    }
+                }
+                Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+                    println!("Received disconnected");
                     return;
                 }
             }
-            Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
-                println!("Received disconnected");
-                return;
-            }
         }
     });
-    if !bj_client::actions::is_deck_dealt(&player, &program, &connection).unwrap() {
-        println!("Waiting for dealer do create the deck");
-        deck_created.acquire();
+    if bj_client::actions::is_waiting_for_player_seed(
+        &player.pubkey(),
+        &table,
+        &program,
+        &connection,
+    )
+    .unwrap()
+    {
+        match bj_client::actions::commit_seed(&player, &table, &program, &connection) {
+            Ok(player_seed) => *seed.lock().unwrap() = Some(player_seed),
+            Err(e) => println!("Shuffle seed is not committed ({})", e),
+        }
     }
-    println!("Cards are dealt, now game can begin");
-    let init_player_hand =
-        bj_client::actions::get_init_status(&player, &program, &connection).unwrap();
-    if init_player_hand > 21 {
-        println!("PLAYER BUSTED");
-        //notify dealer and finish
-        bj_client::actions::busted(&player, &program, &connection, utils::PLAYER_BUSTED).unwrap();
-    } else {
-        loop {
-            println!("Enter option:");
-            println!("1) Hit");
-            println!("2) Stand");
+    let mut waiting =
+        !bj_client::actions::is_players_turn(&player.pubkey(), &table, &program, &connection)
+            .unwrap();
+    if waiting {
+        println!("Waiting for the turn of the player's seat");
+    }
+    let rules = bj_client::actions::get_table_rules(&table, &program, &connection).unwrap();
+    loop {
+        if waiting {
+            turn.acquire();
+        }
+        waiting = true;
+        if *phase.lock().unwrap() == utils::Phase::Settled {
+            break;
+        }
+        println!("Player's turn");
+        let total =
+            bj_client::actions::get_init_status(&player.pubkey(), &table, &program, &connection)
+                .unwrap();
+        // dealer shows an ace, so the player decides on the insurance first, or on
+        // the early surrender under an ace or a ten
+        let offer = bj_client::actions::get_insurance_offer(
+            &player.pubkey(),
+            &table,
+            &program,
+            &connection,
+        )
+        .unwrap();
+        if let Some((up_card, max)) = offer {
             let mut line = String::new();
-            std::io::stdin().read_line(&mut line).unwrap();
-            line = line.trim().to_string();
-            if line == "1" {
-                bj_client::actions::hit(&player, &program, &connection, utils::PLAYER_HIT).unwrap();
-                hit_sem.acquire();
-                if *busted.lock().unwrap() {
-                    println!("PLAYER BUSTED");
-                    //notify dealer and finish
-                    bj_client::actions::busted(
-                        &player,
-                        &program,
-                        &connection,
-                        utils::PLAYER_BUSTED,
-                    )
-                    .unwrap();
-                    break;
-                }
-            } else if line == "2" {
-                bj_client::actions::stand(&player, &program, &connection, utils::PLAYER_STAND)
-                    .unwrap();
-                println!("Wait dealer to finish");
-                //wait for dealer to finish
-                dealer_finished.acquire();
-                break;
-            } else if line == "3" {
-                bj_client::actions::clear_data(&player, &program, &connection).unwrap();
-                break;
+            let ace = up_card.rank == utils::card::Rank::Ace;
+            // early surrender is taken before the insurance is decided
+            if rules.surrender == utils::SurrenderRule::Early {
+                println!("Dealer shows {}. Surrender? (y/n)", up_card);
+                std::io::stdin().read_line(&mut line).unwrap();
+            }
+            if line.trim() == "y" {
+                bj_client::actions::surrender(&player, &table, &program, &connection).unwrap();
+            } else {
+                line.clear();
+                let (amount, even_money) = if !ace {
+                    // no insurance under a ten
+                    (0, false)
+                } else if total == 21 {
+                    println!("Dealer shows an ace. Take even money? (y/n)");
+                    std::io::stdin().read_line(&mut line).unwrap();
+                    (0, line.trim() == "y")
+                } else {
+                    println!(
+                        "Dealer shows an ace. Enter insurance up to {} (0 to decline):",
+                        max
+                    );
+                    std::io::stdin().read_line(&mut line).unwrap();
+                    (line.trim().parse().unwrap_or(0).min(max), false)
+                };
+                bj_client::actions::insurance(
+                    &player,
+                    &table,
+                    &program,
+                    &connection,
+                    amount,
+                    even_money,
+                )
+                .unwrap();
             }
+            continue;
+        }
+        println!("Enter option:");
+        println!("1) Hit");
+        println!("2) Stand");
+        println!("3) Double");
+        println!("4) Split");
+        if rules.surrender != utils::SurrenderRule::Disabled {
+            println!("5) Surrender");
+        }
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).unwrap();
+        line = line.trim().to_string();
+        let action = match line.as_str() {
+            "1" => bj_client::actions::hit(&player, &table, &program, &connection),
+            "2" => bj_client::actions::stand(&player, &table, &program, &connection),
+            "3" => bj_client::actions::double(&player, &table, &program, &connection),
+            "4" => bj_client::actions::split(&player, &table, &program, &connection),
+            "5" => bj_client::actions::surrender(&player, &table, &program, &connection),
+            _ => {
+                waiting = false;
+                continue;
+            }
+        };
+        // doubles, splits and surrenders are refused by the table rules
+        if let Err(e) = action {
+            println!("Action not allowed: {}", e);
+            waiting = false;
         }
     }
     //finish player
     *(end_recv.lock().unwrap()) = true;
     recv_thread.join().unwrap();
+    // dealer may pay out, and free the seats, first
+    if let Err(e) = bj_client::actions::payout(&player, &table, &program, &connection) {
+        println!("Payout is not sent ({})", e);
+    }
+    // bet of a seat which was not dealt stays in escrow, and is taken back before leaving
+    if let Err(e) = bj_client::actions::withdraw_bet(&player, &table, &program, &connection) {
+        println!("Bet is not withdrawn ({})", e);
+    }
+    println!(
+        "Player balance after the round: {}.",
+        balance(&player, &connection, mint.as_ref())
+    );
     // must be called, because pubsubclient currently can't unsubscribe from the network.
-    bj_client::actions::clear_data(&player, &program, &connection).unwrap();
+    if let Err(e) = bj_client::actions::clear_data(&player, &table, &program, &connection) {
+        println!("Seat is already free ({})", e);
+    }
     exit(0);
 }
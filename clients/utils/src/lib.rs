@@ -1,4 +1,3 @@
-use borsh::{BorshDeserialize, BorshSerialize};
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signer::keypair::{read_keypair_file, Keypair};
 use std::path::PathBuf;
@@ -32,38 +31,69 @@ pub enum Error {
     FeeCaluclatorError(String),
     #[error("Custom error: ({0})")]
     Error(String),
+
+    // Errors returned by the blackjack program, decoded by `decode_program_error`.
+    #[error("blackjack program error: ({0})")]
+    Program(BlackJackError),
+    #[error("unknown blackjack program error: ({0})")]
+    UnknownProgramError(u32),
+}
+
+/// Decodes the custom error CODE returned by the blackjack program.
+pub fn decode_program_error(code: u32) -> Error {
+    match BlackJackError::try_from(code) {
+        Ok(error) => Error::Program(error),
+        Err(code) => Error::UnknownProgramError(code),
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-/// The schema for storage in blackjack accounts. This is what
-/// is serialized into the account and later updated.
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub struct BlackJackAccountDataSchema {
-    pub last_operation: u8, // last operation done on account
-    //initial dealer cards, at the game's beginning.
-    pub dealer_hand: u8,     //this card is not visible to players.
-    pub dealer_start2: u8,   // this card is visible to players.
-    pub player_hand: u8,     // contatins sum of the player's cards.
-    pub current_card: usize, //current index inside the deck
-    pub cards: Vec<u8>,
+/// Player's hands of SEAT, numbered in the order they are played, with their outcomes
+/// once settled.
+pub fn format_hands(seat: &Seat) -> String {
+    let hands: Vec<String> = seat
+        .hands
+        .iter()
+        .enumerate()
+        .map(|(i, hand)| match hand.outcome {
+            Some(outcome) => format!(
+                "{}) {} {:?}",
+                i + 1,
+                card::format_hand(&hand.cards),
+                outcome
+            ),
+            None => format!("{}) {}", i + 1, card::format_hand(&hand.cards)),
+        })
+        .collect();
+    hands.join(", ")
 }
-//instruction codes. Used in program, for switching.
-pub const SEND_DECK: u8 = 0;
-pub const DEAL: u8 = 1;
-pub const REQUEST_NEW_DECK: u8 = 2;
-pub const CLEAR_DATA: u8 = 3;
-pub const PLAYER_HIT: u8 = 4;
-pub const PLAYER_STAND: u8 = 5;
-pub const DEALER_HIT: u8 = 6;
-pub const DEALER_STAND: u8 = 7;
-pub const PLAYER_BUSTED: u8 = 8;
-pub const DEALER_BUSTED: u8 = 9;
-#[derive(BorshSerialize, BorshDeserialize)]
-pub struct SendDeck {
-    pub deck: Vec<u8>,
+
+/// Hands of every seat of TABLE, in the order of the seats.
+pub fn format_player_hands(table: &BlackJackAccountData) -> String {
+    let seats: Vec<String> = table
+        .seats
+        .iter()
+        .enumerate()
+        .map(|(i, seat)| format!("seat {}: {}", i + 1, format_hands(seat)))
+        .collect();
+    seats.join("; ")
 }
 
+// State of the table, the card model and the constants of the game are shared with
+// the program through the core crate.
+pub use black_jack_core::card;
+pub use black_jack_core::commitment;
+pub use black_jack_core::constants::*;
+pub use black_jack_core::state::*;
+// Instructions and their payloads, and the program's errors, are defined by the program,
+// and shared with it.
+pub use black_jack::error::BlackJackError;
+pub use black_jack::instructions::{
+    BlackJackInstruction, CommitSeed, CreateTable, HouseTransfer, Insurance, OpenedCard,
+    PlaceBet, RevealSeed, RevealedCard, SendDeck,
+};
+
 /// Parses and returns the Solana yaml config on the system.
 pub fn get_config() -> Result<yaml_rust::Yaml> {
     let path = match home::home_dir() {
@@ -128,7 +158,7 @@ pub fn get_player_wallet() -> Result<Keypair> {
             ))
         }
     };
-    let player_path = PathBuf::from("player_wallet/keypair.json");
+    let player_path = PathBuf::from("player/player_wallet/keypair.json");
     let player_path = player_path.as_path();
     proj_root.push(player_path);
     read_keypair_file(proj_root).map_err(|e| {
@@ -137,39 +167,46 @@ pub fn get_player_wallet() -> Result<Keypair> {
     })
 }
 
-/// Gets the seed used to generate accounts. If you'd like to
-/// force this program to generate a new  account you can change this value.
-pub fn get_account_seed() -> &'static str {
-    "black_jack"
+/// Gets the wallet from the keypair file at PATH, for players other than the "player wallet".
+pub fn get_wallet(path: &str) -> Result<Keypair> {
+    read_keypair_file(path).map_err(|e| {
+        println!("{:?}", e);
+        Error::InvalidKeypairFile(format!("Invalid keyfile location ({})", path))
+    })
+}
+
+/// Removes the option NAME and its value from the command line ARGS, and returns the value.
+pub fn flag_value(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let i = args.iter().position(|arg| arg == name)?;
+    if i + 1 >= args.len() {
+        return None;
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value)
+}
+
+/// Derives the address of the table with INDEX of the DEALER. A dealer can run
+/// many tables, each of them created by the program at its own address.
+pub fn get_table_address(dealer: &Pubkey, index: u16, program: &Pubkey) -> Pubkey {
+    BlackJackAccountData::address(dealer, index, program).0
 }
 
-/// Derives and returns the account public key for a given
-/// CLIENT, PROGRAM combination.
-pub fn get_account_public_key(player: &Pubkey, program: &Pubkey) -> Result<Pubkey> {
-    Ok(Pubkey::create_with_seed(
-        player,
-        get_account_seed(),
-        program,
-    )?)
+/// Derives the house vault of TABLE, which holds the bankroll covering the bets.
+pub fn get_house_address(table: &Pubkey, program: &Pubkey) -> Pubkey {
+    black_jack_core::house::house_address(table, program).0
 }
 
-/// Determines and reports the size of blackjack account data.
-pub fn get_blackjack_data_size() -> Result<usize> {
+/// Derives the randomness account of TABLE, into which the oracle writes the randomness
+/// of every drawn card.
+pub fn get_randomness_address(table: &Pubkey, program: &Pubkey) -> Pubkey {
+    black_jack_core::oracle::randomness_address(table, program).0
+}
+
+/// Determines and reports the size of blackjack account data, for a shoe of DECKS.
+pub fn get_blackjack_data_size(decks: u8) -> Result<usize> {
     println!("Calculating blackjack account size");
-    let mut vec = Vec::new();
-    for i in 0..52 {
-        vec.push(i);
-    }
-    let encoded = BlackJackAccountDataSchema {
-        cards: vec,
-        dealer_hand: 0,
-        dealer_start2: 0,
-        player_hand: 0,
-        current_card: 0,
-        last_operation: 0,
-    }
-    .try_to_vec()
-    .map_err(|e| Error::SerializationError(e))?;
-    println!("Size: {}", encoded.len());
-    Ok(encoded.len())
+    let size = BlackJackAccountData::max_size(decks).map_err(Error::SerializationError)?;
+    println!("Size: {}", size);
+    Ok(size)
 }
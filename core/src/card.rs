@@ -0,0 +1,200 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use borsh::{BorshDeserialize, BorshSerialize};
+use core::fmt;
+
+// Card model, shared by the program and its clients.
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Suit {
+    Spades,
+    Hearts,
+    Diamonds,
+    Clubs,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rank {
+    Ace,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Card {
+    pub rank: Rank,
+    pub suit: Suit,
+}
+
+pub const SUITS: [Suit; 4] = [Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs];
+pub const RANKS: [Rank; 13] = [
+    Rank::Ace,
+    Rank::Two,
+    Rank::Three,
+    Rank::Four,
+    Rank::Five,
+    Rank::Six,
+    Rank::Seven,
+    Rank::Eight,
+    Rank::Nine,
+    Rank::Ten,
+    Rank::Jack,
+    Rank::Queen,
+    Rank::King,
+];
+
+/// Card on POSITION of an unshuffled deck: all ranks of spades, then hearts, diamonds and clubs.
+pub fn card_at(position: usize) -> Card {
+    Card {
+        rank: RANKS[position % RANKS.len()],
+        suit: SUITS[position / RANKS.len() % SUITS.len()],
+    }
+}
+
+impl Card {
+    /// Position of the card in an unshuffled deck, the inverse of `card_at` within one deck.
+    pub fn index(&self) -> usize {
+        self.suit as usize * RANKS.len() + self.rank as usize
+    }
+}
+
+impl Rank {
+    /// Blackjack value of the rank. Face cards are worth 10, ace is counted as 1 here;
+    /// counting it as 11 is decided by `add_card`.
+    pub fn value(&self) -> u8 {
+        match self {
+            Rank::Ace => 1,
+            Rank::Two => 2,
+            Rank::Three => 3,
+            Rank::Four => 4,
+            Rank::Five => 5,
+            Rank::Six => 6,
+            Rank::Seven => 7,
+            Rank::Eight => 8,
+            Rank::Nine => 9,
+            Rank::Ten | Rank::Jack | Rank::Queen | Rank::King => 10,
+        }
+    }
+}
+
+/// Add CARD to a hand whose best total is TOTAL. SOFT tells if one ace in the hand
+/// is currently counted as 11. Returns the new best total and softness.
+/// One ace is counted as 11 whenever that does not bust the hand.
+pub fn add_card(total: u8, soft: bool, card: &Card) -> (u8, bool) {
+    let hard = if soft { total - 10 } else { total } + card.rank.value();
+    let has_ace = soft || card.rank == Rank::Ace;
+    if has_ace && hard + 10 <= 21 {
+        (hard + 10, true)
+    } else {
+        (hard, false)
+    }
+}
+
+/// Best blackjack total of CARDS and whether it is soft.
+pub fn hand_value(cards: &[Card]) -> (u8, bool) {
+    cards.iter().fold((0, false), |(total, soft), card| {
+        add_card(total, soft, card)
+    })
+}
+
+/// Natural blackjack: 21 with the first two cards.
+pub fn is_natural(cards: &[Card]) -> bool {
+    cards.len() == 2 && hand_value(cards).0 == 21
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rank = match self.rank {
+            Rank::Ace => "A",
+            Rank::Two => "2",
+            Rank::Three => "3",
+            Rank::Four => "4",
+            Rank::Five => "5",
+            Rank::Six => "6",
+            Rank::Seven => "7",
+            Rank::Eight => "8",
+            Rank::Nine => "9",
+            Rank::Ten => "10",
+            Rank::Jack => "J",
+            Rank::Queen => "Q",
+            Rank::King => "K",
+        };
+        let suit = match self.suit {
+            Suit::Spades => "\u{2660}",
+            Suit::Hearts => "\u{2665}",
+            Suit::Diamonds => "\u{2666}",
+            Suit::Clubs => "\u{2663}",
+        };
+        write!(f, "{}{}", rank, suit)
+    }
+}
+
+/// Format CARDS together with their blackjack total, e.g. `A♠ 7♥ (soft 18)`.
+pub fn format_hand(cards: &[Card]) -> String {
+    let (total, soft) = hand_value(cards);
+    let cards: Vec<String> = cards.iter().map(|c| c.to_string()).collect();
+    if soft {
+        alloc::format!("{} (soft {})", cards.join(" "), total)
+    } else {
+        alloc::format!("{} ({})", cards.join(" "), total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(ranks: &[Rank]) -> Vec<Card> {
+        ranks
+            .iter()
+            .map(|&rank| Card {
+                rank,
+                suit: Suit::Spades,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_ace_is_counted_as_eleven_when_it_does_not_bust() {
+        assert_eq!(
+            hand_value(&cards(&[Rank::Ace, Rank::Ace, Rank::Nine])),
+            (21, true)
+        );
+        assert_eq!(hand_value(&cards(&[Rank::Ace, Rank::Six])), (17, true));
+        assert_eq!(
+            hand_value(&cards(&[Rank::Ace, Rank::Six, Rank::Ten])),
+            (17, false)
+        );
+        assert_eq!(
+            hand_value(&cards(&[Rank::King, Rank::Queen, Rank::Two])),
+            (22, false)
+        );
+    }
+
+    #[test]
+    fn natural_is_21_with_the_first_two_cards() {
+        assert!(is_natural(&cards(&[Rank::Ace, Rank::King])));
+        assert!(is_natural(&cards(&[Rank::Ten, Rank::Ace])));
+        assert!(!is_natural(&cards(&[Rank::Ace, Rank::Nine])));
+        assert!(!is_natural(&cards(&[Rank::Ten, Rank::Five, Rank::Six])));
+        assert!(!is_natural(&cards(&[Rank::Ace, Rank::Ace, Rank::Nine])));
+        assert!(!is_natural(&[]));
+    }
+
+    #[test]
+    fn card_index_is_the_inverse_of_card_at() {
+        for position in 0..RANKS.len() * SUITS.len() {
+            assert_eq!(card_at(position).index(), position);
+        }
+    }
+}
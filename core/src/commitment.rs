@@ -0,0 +1,137 @@
+use alloc::vec::Vec;
+use solana_program::hash::{hashv, Hash};
+
+use crate::card::Card;
+
+// Deck commitment. Dealer commits to the deck with the Merkle root over
+// salted cards, and reveals every card together with its Merkle proof when
+// the card is consumed. The program verifies the proofs the client builds.
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Hash of the card on deck POSITION, salted with SALT.
+pub fn leaf_hash(position: usize, card: &Card, salt: &[u8; 32]) -> Hash {
+    hashv(&[
+        &[LEAF_PREFIX],
+        &(position as u32).to_le_bytes(),
+        &[card.rank as u8, card.suit as u8],
+        salt,
+    ])
+}
+
+/// Hash of the inner tree node with LEFT and RIGHT children.
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    hashv(&[&[NODE_PREFIX], left.as_ref(), right.as_ref()])
+}
+
+/// Check that LEAF is on POSITION of the tree with LEAF_COUNT leaves and root ROOT.
+/// PROOF contains sibling hashes from the leaf level up. A node without sibling
+/// is moved to the next level unchanged, and has no entry in the proof.
+pub fn verify_proof(
+    root: &[u8; 32],
+    leaf: Hash,
+    position: usize,
+    leaf_count: usize,
+    proof: &[[u8; 32]],
+) -> bool {
+    if position >= leaf_count {
+        return false;
+    }
+    let mut siblings = proof.iter();
+    let mut hash = leaf;
+    let mut index = position;
+    let mut width = leaf_count;
+    while width > 1 {
+        if index % 2 == 1 {
+            match siblings.next() {
+                Some(s) => hash = node_hash(&Hash::new_from_array(*s), &hash),
+                None => return false,
+            }
+        } else if index + 1 < width {
+            match siblings.next() {
+                Some(s) => hash = node_hash(&hash, &Hash::new_from_array(*s)),
+                None => return false,
+            }
+        }
+        index /= 2;
+        width = width.div_ceil(2);
+    }
+    siblings.next().is_none() && hash.to_bytes() == *root
+}
+
+/// Add LEAF, the leaf on position COUNT, to SUBTREES: the roots of the perfect subtrees
+/// over the COUNT leaves before it, largest first. Subtrees of the same size are joined,
+/// as their levels of the tree join them, so the tree is built as its leaves are opened.
+pub fn push_leaf(subtrees: &mut Vec<[u8; 32]>, count: usize, leaf: Hash) {
+    let mut hash = leaf;
+    let mut joined = count;
+    while joined % 2 == 1 {
+        match subtrees.pop() {
+            Some(left) => hash = node_hash(&Hash::new_from_array(left), &hash),
+            None => break,
+        }
+        joined /= 2;
+    }
+    subtrees.push(hash.to_bytes());
+}
+
+/// Root of the tree over the leaves of SUBTREES, which `push_leaf` built. A node without
+/// sibling is moved up unchanged, so the smaller subtrees join from the right.
+pub fn subtrees_root(subtrees: &[[u8; 32]]) -> Option<[u8; 32]> {
+    let (last, rest) = subtrees.split_last()?;
+    let root = rest
+        .iter()
+        .rev()
+        .fold(Hash::new_from_array(*last), |right, left| {
+            node_hash(&Hash::new_from_array(*left), &right)
+        });
+    Some(root.to_bytes())
+}
+
+/// Commitment to a shuffle SEED.
+pub fn seed_hash(seed: &[u8; 32]) -> [u8; 32] {
+    hashv(&[seed]).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Rank, Suit};
+
+    // Root of the tree over LEAVES, built level by level as the dealer builds it.
+    fn level_root(leaves: &[Hash]) -> [u8; 32] {
+        let mut level = leaves.to_vec();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+        }
+        level[0].to_bytes()
+    }
+
+    #[test]
+    fn opened_leaves_build_the_root_of_the_commitment() {
+        let card = Card {
+            rank: Rank::Ace,
+            suit: Suit::Spades,
+        };
+        let leaves: Vec<Hash> = (0..60)
+            .map(|i| leaf_hash(i, &card, &[i as u8; 32]))
+            .collect();
+        let mut subtrees = Vec::new();
+        assert_eq!(subtrees_root(&subtrees), None);
+        for (count, leaf) in leaves.iter().enumerate() {
+            push_leaf(&mut subtrees, count, *leaf);
+            assert_eq!(subtrees.len(), (count + 1).count_ones() as usize);
+            assert_eq!(
+                subtrees_root(&subtrees),
+                Some(level_root(&leaves[..=count]))
+            );
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Operation codes, recorded as the last operation of the table. Clients follow
+// the table through them. Instructions themselves are sent as the program's
+// `BlackJackInstruction`.
+pub const SEND_DECK: u8 = 0;
+pub const DEAL: u8 = 1;
+pub const REQUEST_NEW_DECK: u8 = 2;
+pub const CLEAR_DATA: u8 = 3;
+pub const PLAYER_HIT: u8 = 4;
+pub const PLAYER_STAND: u8 = 5;
+// Not instructions since the dealer plays by the table rules. Last operation when
+// the dealer's hand is played out, or the hit busts the hand, and the round is settled.
+pub const DEALER_HIT: u8 = 6;
+pub const DEALER_STAND: u8 = 7;
+pub const PLAYER_BUSTED: u8 = 8;
+pub const DEALER_BUSTED: u8 = 9;
+pub const REVEAL_CARD: u8 = 10;
+// Not an instruction. Last operation while requested cards wait to be revealed by the dealer.
+pub const AWAITING_REVEAL: u8 = 11;
+pub const PLAYER_COMMIT_SEED: u8 = 12;
+pub const PLAYER_REVEAL_SEED: u8 = 13;
+pub const DEALER_REVEAL_SEED: u8 = 14;
+pub const REQUEST_ORACLE_DECK: u8 = 15;
+pub const JOIN_TABLE: u8 = 16;
+pub const SET_RULES: u8 = 17;
+pub const DEALER_PLAY: u8 = 18;
+pub const PLACE_BET: u8 = 19;
+pub const PAYOUT: u8 = 20;
+pub const SET_MINT: u8 = 21;
+pub const HOUSE_DEPOSIT: u8 = 22;
+pub const HOUSE_WITHDRAW: u8 = 23;
+pub const PLAYER_DOUBLE: u8 = 24;
+pub const PLAYER_SPLIT: u8 = 25;
+pub const INSURANCE: u8 = 26;
+pub const PLAYER_SURRENDER: u8 = 27;
+pub const CREATE_TABLE: u8 = 28;
+pub const DEALER_PEEK: u8 = 29;
+pub const DRAW_ORACLE_CARD: u8 = 30;
+// Not an instruction. Last operation when the committed shoe fails its check, or the dealer
+// misses its deadline, and the house forfeits its cover of the round.
+pub const SHOE_FORFEIT: u8 = 31;
+pub const OPEN_SHOE: u8 = 32;
+pub const WITHDRAW_BET: u8 = 33;
+pub const DROP_SEEDS: u8 = 34;
+pub const CLAIM_FORFEIT: u8 = 35;
+
+// public constants
+pub const CARD_NUMBER: u8 = 52; // cards in one deck
+pub const MAX_DECKS: u8 = 8;
+pub const MAX_SEATS: usize = 7; // players seated at one table
+
+// Slots the seats have to commit to their seeds of a new shoe, and again to reveal them
+// once the dealer's seed is revealed.
+pub const SEED_SLOTS: u64 = 150;
+// Slots the dealer has for its next step of the dealt round, after which the players claim
+// the house's cover. Longer than SEED_SLOTS, as the dealer waits for the seeds of a new shoe.
+pub const DEALER_SLOTS: u64 = 300;
+// Seed of the table address, derived by the program from the dealer's wallet
+// and the index of the table.
+pub const TABLE_SEED: &[u8] = b"black_jack";
+/// Most cards a hand can hold from a shoe of DECKS: the smallest cards of the shoe
+/// up to 21, and one more card which busts the hand. From one deck, four aces, four twos
+/// and three threes make 21. Each of the player's hands holds at most this many.
+pub fn max_hand_cards(decks: u8) -> usize {
+    let mut total = 0;
+    let mut cards = 0;
+    for value in 1..=10u32 {
+        // four suits of every rank, and four ranks worth 10
+        let count = if value == 10 { 16 } else { 4 } * decks as u32;
+        for _ in 0..count {
+            if total + value > 21 {
+                return cards + 1;
+            }
+            total += value;
+            cards += 1;
+        }
+    }
+    cards + 1
+}
+// Most hands a player can hold by splitting. Bounds the worst case of a bet.
+pub const MAX_SPLIT_HANDS: u64 = 4;
+
+/// Most the house can lose on BET: every split hand is doubled and wins 1:1.
+/// This is more than a natural pays. None if it overflows.
+pub fn worst_case_payout(bet: u64) -> Option<u64> {
+    bet.checked_mul(2 * MAX_SPLIT_HANDS)
+}
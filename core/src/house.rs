@@ -0,0 +1,11 @@
+use solana_program::pubkey::Pubkey;
+
+// House vault of a table, which holds the bankroll covering the bets. The program
+// owns the vault at an address derived from the table.
+
+pub const HOUSE_SEED: &[u8] = b"house";
+
+/// House vault of the TABLE account, and its bump seed.
+pub fn house_address(table: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[HOUSE_SEED, table.as_ref()], program_id)
+}
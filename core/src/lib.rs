@@ -0,0 +1,14 @@
+//! State of the blackjack table, shared by the on-chain program and its clients: the card
+//! model and hand evaluator, the table account, the deck commitment, the house vault and
+//! oracle randomness addresses, and the constants of the game. Both sides
+//! serialize the same types, so the layout of the account can't drift between them.
+#![no_std]
+
+extern crate alloc;
+
+pub mod card;
+pub mod commitment;
+pub mod constants;
+pub mod house;
+pub mod oracle;
+pub mod state;
@@ -0,0 +1,12 @@
+use solana_program::pubkey::Pubkey;
+
+// Randomness account of a table, into which the oracle writes a fresh random value for
+// every card of the oracle's shoe. The program creates it at an address derived from
+// the table, so the dealer can't pick the account the cards are drawn from.
+
+pub const ORACLE_SEED: &[u8] = b"oracle";
+
+/// Randomness account of the TABLE account, and its bump seed.
+pub fn randomness_address(table: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ORACLE_SEED, table.as_ref()], program_id)
+}
@@ -0,0 +1,645 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use borsh::maybestd::io;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+use crate::card::{is_natural, Card, Rank, Suit};
+use crate::constants::{
+    max_hand_cards, worst_case_payout, CARD_NUMBER, MAX_SEATS, MAX_SPLIT_HANDS, TABLE_SEED,
+};
+
+/// House rules of the table, chosen by the dealer between rounds.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableRules {
+    pub hit_soft_17: bool,       // dealer hits soft 17, otherwise stands on all 17s
+    pub double_on: DoubleTotals, // totals of the first two cards the player can double on
+    pub resplit: bool,           // split hands can be split again, up to MAX_SPLIT_HANDS hands
+    pub split_aces_one_card: bool, // split aces receive one card each, and can't be played
+    pub surrender: SurrenderRule, // when the player can give up half the stake
+    pub decks: u8,               // decks in the shoe, from 1 to MAX_DECKS
+    pub cut_card: u16,           // cards dealt from the shoe before it must be reshuffled
+}
+
+impl TableRules {
+    /// Cards in a shoe of the table's decks.
+    pub fn shoe_cards(&self) -> u16 {
+        self.decks as u16 * CARD_NUMBER as u16
+    }
+
+    /// Cut card placed after three quarters of the shoe.
+    pub fn default_cut_card(&self) -> u16 {
+        self.shoe_cards() * 3 / 4
+    }
+}
+
+/// When the player can surrender, as its first decision on the hand.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurrenderRule {
+    Disabled,
+    Late,  // after the dealer checks the hole card, so not against the dealer's natural
+    Early, // before the dealer checks the hole card, even against the dealer's natural
+}
+
+/// Totals of the first two cards on which the player can double down.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoubleTotals {
+    Any,
+    NineToEleven,
+    TenOrEleven,
+}
+
+impl DoubleTotals {
+    /// Check if the player can double on a hand with TOTAL.
+    pub fn allows(&self, total: u8) -> bool {
+        match self {
+            DoubleTotals::Any => true,
+            DoubleTotals::NineToEleven => (9..=11).contains(&total),
+            DoubleTotals::TenOrEleven => (10..=11).contains(&total),
+        }
+    }
+}
+
+/// Where the cards come from.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeckSource {
+    Committed, // deck committed by the dealer, cards are revealed by the dealer
+    Oracle,    // every card is drawn on-chain with fresh oracle randomness, from the cards left
+}
+
+/// Hand that receives a card once the dealer reveals it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hand {
+    Player(u8, u8), // index of the seat, and into the seat's hands
+    Dealer,
+    // Dealer's hole card. Its position is reserved when it is dealt, and the card
+    // is revealed from that position when it is requested again.
+    Hole,
+}
+
+/// One of the player's hands. Player holds more than one hand after splitting a pair,
+/// and each hand has its own bet and outcome.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlayerHand {
+    pub cards: Vec<Card>,         // cards of the hand, in the order they are dealt
+    pub total: u8,                // blackjack total of the cards
+    pub bet: u64,                 // stake on this hand
+    pub split: bool,              // hand comes from a split, so two cards of 21 are not a natural
+    pub outcome: Option<Outcome>, // set when the round is settled
+}
+
+impl PlayerHand {
+    /// Empty hand with BET, dealt from the start of the round or from a split.
+    pub fn new(bet: u64, split: bool) -> Self {
+        PlayerHand {
+            cards: Vec::new(),
+            total: 0,
+            bet,
+            split,
+            outcome: None,
+        }
+    }
+
+    /// Natural blackjack. Hands which come from a split have none.
+    pub fn is_natural(&self) -> bool {
+        !self.split && is_natural(&self.cards)
+    }
+}
+
+/// Seat at the table, taken by a player. Every seat has its own stake and hands,
+/// and takes its turn in the order of the seats.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Seat {
+    pub player: Pubkey, // wallet which took the seat
+    // Stakes held in escrow by the table account, on top of its rent,
+    // or by the table's token vault.
+    pub bet: u64,          // player's stake, on all of its hands
+    pub dealer_stake: u64, // house's cover of the bet, enough to pay its worst case
+    pub insurance: u64,    // player's side bet against the dealer's natural
+    pub even_money: bool,  // player's natural is paid 1:1, whatever the dealer holds
+    // one hand, and one more for every split. Empty until the seat is dealt in.
+    pub hands: Vec<PlayerHand>,
+    pub active_hand: u8, // index of the hand the player is playing
+    // Player's shuffle seed of the committed shoe. Seat is dealt from the shoe only
+    // if its seed is mixed into the order of the cards.
+    pub seed_commitment: Option<[u8; 32]>,
+    pub seed: Option<[u8; 32]>,
+}
+
+impl Seat {
+    /// Empty seat taken by PLAYER, dealt in from the next round.
+    pub fn new(player: Pubkey) -> Self {
+        Seat {
+            player,
+            bet: 0,
+            dealer_stake: 0,
+            insurance: 0,
+            even_money: false,
+            hands: Vec::new(),
+            active_hand: 0,
+            seed_commitment: None,
+            seed: None,
+        }
+    }
+
+    /// Hand the player is playing.
+    pub fn active(&self) -> Option<&PlayerHand> {
+        self.hands.get(self.active_hand as usize)
+    }
+
+    /// Seat is dealt in the round, and its escrow is not paid out yet. House covers the bet
+    /// when the round is dealt, so a bet placed for the next round has no cover yet.
+    pub fn unpaid(&self) -> bool {
+        self.dealer_stake > 0
+    }
+
+    /// Seat still plays a hand against the dealer's hand. Busted and surrendered hands,
+    /// naturals and even money are decided without the dealer playing.
+    pub fn live(&self) -> bool {
+        !self.even_money
+            && self
+                .hands
+                .iter()
+                .any(|h| h.outcome.is_none() && h.total <= 21 && !h.is_natural())
+    }
+
+    /// Stakes of the seat's escrow which go to the player and back to the house
+    /// by the outcomes of the player's hands, and the insurance which pays 2:1
+    /// on the dealer's natural. Forfeited round pays the player the whole escrow, with
+    /// the house's cover. Player never gets more than the escrow.
+    pub fn payouts(&self, dealer_natural: bool) -> (u64, u64) {
+        let escrow = self.bet + self.insurance + self.dealer_stake;
+        if self
+            .hands
+            .iter()
+            .any(|h| h.outcome == Some(Outcome::Forfeit))
+        {
+            return (escrow, 0);
+        }
+        let insurance = if dealer_natural {
+            3 * self.insurance
+        } else {
+            0
+        };
+        let player = self
+            .hands
+            .iter()
+            .map(|hand| match hand.outcome {
+                Some(Outcome::Blackjack) => hand.bet + hand.bet * 3 / 2,
+                Some(Outcome::PlayerWin) => 2 * hand.bet,
+                Some(Outcome::Push) => hand.bet,
+                Some(Outcome::Surrender) => hand.bet / 2,
+                Some(Outcome::DealerWin) | Some(Outcome::Forfeit) | None => 0,
+            })
+            .sum::<u64>()
+            .saturating_add(insurance)
+            .min(escrow);
+        (player, escrow - player)
+    }
+}
+
+/// Phase of the game round. Every instruction is valid only in some phases.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    WaitingForDeck, // deck is being committed and shuffled, nothing is dealt yet
+    Dealt,          // initial cards are requested, and wait to be revealed
+    // dealer shows an ace, every seat decides on insurance or even money. Under a ten,
+    // every seat decides on early surrender, when the rules allow it.
+    Insurance,
+    Peek,       // dealer shows an ace or a ten, and tells if the hole card makes a natural
+    PlayerTurn, // seats take turns, each hits until it stands or busts
+    DealerTurn, // dealer hits until it stands or busts
+    Settled,    // round is over, next round can be dealt from the same deck
+}
+
+/// Dealer's natural, as the peek at the hole card tells it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Natural {
+    Possible, // hole card is not peeked at, or it is revealed as a natural
+    Denied,   // dealer told it has none, a natural revealed later forfeits its hand
+    Excluded, // hole card is dealt again from a new shoe after the peek, and makes no natural
+}
+
+/// Result of a settled round, decided by the program.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    PlayerWin,
+    DealerWin,
+    Push,
+    Blackjack, // player wins with a natural, dealer has none
+    Surrender, // player gives up the hand, and gets back half the stake
+    // round is dealt from a shoe which failed its check, or the dealer missed its deadline,
+    // and the house forfeits its cover
+    Forfeit,
+}
+
+// The type of state managed by the program. Clients decode the table account
+// with the same type.
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct BlackJackAccountData {
+    pub dealer: Pubkey, // wallet which created the table, and sends the decks
+    pub index: u16,     // index of the table among the tables of the dealer
+    pub bump: u8,       // bump seed of the table address, to sign for the table
+    pub rules: TableRules,
+    pub mint: Option<Pubkey>, // mint of the staked tokens. Lamports are staked if not set.
+    pub last_operation: u8,   // last operation done on account
+    pub phase: Phase,
+    // Phase of the round interrupted by the end of the shoe. Round waits for a new shoe,
+    // and resumes in this phase.
+    pub interrupted: Option<Phase>,
+    pub seats: Vec<Seat>, // up to MAX_SEATS, in the order of their turns
+    pub active_seat: u8,  // index of the seat whose turn it is
+    pub dealer_hand: u8,  // contains blackjack total of the dealer's cards.
+    //dealer's revealed cards. First one is the up card, second one the hole card once revealed.
+    pub dealer_cards: Vec<Card>,
+    // number of the hole card in the shoe, while it is dealt face down. Index into
+    // deck_order of the committed deck.
+    pub hole_card: Option<usize>,
+    pub natural: Natural, // what the peek at the hole card told about the dealer's natural
+    pub deck_source: DeckSource,
+    pub deck_root: [u8; 32], // commitment to the deck, received with SEND_DECK
+    pub shoe_cards: u16,     // cards in the current shoe, fixed by the rules when it is sent
+    pub cut_card: u16,       // cut card of the current shoe, fixed by the rules when it is sent
+    pub dealer_seed_commitment: [u8; 32], // received with SEND_DECK
+    pub dealer_seed: Option<[u8; 32]>,
+    // slot until which the seats commit to their seeds, and once the dealer's seed
+    // is revealed, until which they reveal them
+    pub seed_deadline: u64,
+    //order of dealing the deck positions. Empty until the dealer's seed and the seeds
+    //of the seats are revealed, and for the oracle's shoe, which has no order.
+    pub deck_order: Vec<u16>,
+    pub current_card: usize, //number of cards revealed from the deck, index into deck_order
+    // how many of every card, by its index in a deck, are revealed from the current shoe.
+    // Cards of the oracle's shoe are drawn from the rest. Counted again from zero
+    // when the dealer opens the ended committed shoe.
+    pub dealt_cards: Vec<u8>,
+    pub opened: u16, // cards of the ended committed shoe opened by the dealer, in deck order
+    // roots of the perfect subtrees over the opened cards, largest first
+    pub opening: Vec<[u8; 32]>,
+    pub forfeited: bool, // shoe failed its check, and is not dealt from again
+    // slot until which the dealer takes its next step of the dealt round, after which the
+    // players claim the round
+    pub dealer_deadline: u64,
+    pub pending_operation: u8, // operation which waits for cards to be revealed
+    pub pending_draws: Vec<Hand>, // hands waiting for cards, in the order of dealing
+}
+
+impl BlackJackAccountData {
+    /// Address of the table of DEALER with INDEX, derived by PROGRAM_ID, and its bump seed.
+    /// Dealer runs as many tables as it creates, each at its own index.
+    pub fn address(dealer: &Pubkey, index: u16, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[TABLE_SEED, dealer.as_ref(), &index.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// Deserialize account data. Account is sized for the largest state,
+    /// so trailing bytes are ignored.
+    pub fn unpack(data: &[u8]) -> io::Result<Self> {
+        Self::deserialize(&mut &data[..])
+    }
+
+    /// New table of DEALER with INDEX and the BUMP seed of its address, for a shoe of DECKS.
+    /// No seat is taken, and no shoe is sent yet. Rules are the plainest ones, with the cut
+    /// card after three quarters of the shoe.
+    pub fn new(dealer: Pubkey, index: u16, bump: u8, decks: u8) -> Self {
+        let mut rules = TableRules {
+            hit_soft_17: false,
+            double_on: DoubleTotals::Any,
+            resplit: false,
+            split_aces_one_card: false,
+            surrender: SurrenderRule::Disabled,
+            decks,
+            cut_card: 0,
+        };
+        rules.cut_card = rules.default_cut_card();
+        BlackJackAccountData {
+            dealer,
+            index,
+            bump,
+            rules,
+            mint: None,
+            last_operation: 0,
+            phase: Phase::WaitingForDeck,
+            interrupted: None,
+            seats: Vec::new(),
+            active_seat: 0,
+            dealer_hand: 0,
+            dealer_cards: Vec::new(),
+            hole_card: None,
+            natural: Natural::Possible,
+            deck_source: DeckSource::Committed,
+            deck_root: [0; 32],
+            shoe_cards: 0,
+            cut_card: 0,
+            dealer_seed_commitment: [0; 32],
+            dealer_seed: None,
+            seed_deadline: 0,
+            deck_order: Vec::new(),
+            current_card: 0,
+            dealt_cards: Vec::new(),
+            opened: 0,
+            opening: Vec::new(),
+            forfeited: false,
+            dealer_deadline: 0,
+            pending_operation: 0,
+            pending_draws: Vec::new(),
+        }
+    }
+
+    /// Size of the account data in its largest state, for a shoe of DECKS: every seat
+    /// is taken and split into the most hands, and every hand holds the most cards.
+    /// Table account is created with this size.
+    pub fn max_size(decks: u8) -> io::Result<usize> {
+        let hand = vec![
+            Card {
+                rank: Rank::Ace,
+                suit: Suit::Spades,
+            };
+            max_hand_cards(decks)
+        ];
+        let largest = BlackJackAccountData {
+            dealer: Pubkey::default(),
+            index: 0,
+            bump: 0,
+            rules: TableRules {
+                hit_soft_17: true,
+                double_on: DoubleTotals::Any,
+                resplit: true,
+                split_aces_one_card: true,
+                surrender: SurrenderRule::Early,
+                decks,
+                cut_card: 0,
+            },
+            mint: Some(Pubkey::default()),
+            dealer_hand: 0,
+            dealer_cards: hand.clone(),
+            hole_card: Some(0),
+            natural: Natural::Possible,
+            seats: vec![
+                Seat {
+                    player: Pubkey::default(),
+                    bet: 0,
+                    dealer_stake: 0,
+                    insurance: 0,
+                    even_money: false,
+                    hands: vec![
+                        PlayerHand {
+                            cards: hand,
+                            total: 0,
+                            bet: 0,
+                            split: true,
+                            outcome: Some(Outcome::Blackjack),
+                        };
+                        MAX_SPLIT_HANDS as usize
+                    ],
+                    active_hand: 0,
+                    seed_commitment: Some([0; 32]),
+                    seed: Some([0; 32]),
+                };
+                MAX_SEATS
+            ],
+            active_seat: 0,
+            deck_source: DeckSource::Committed,
+            deck_root: [0; 32],
+            shoe_cards: 0,
+            cut_card: 0,
+            dealer_seed_commitment: [0; 32],
+            dealer_seed: Some([0; 32]),
+            seed_deadline: 0,
+            deck_order: vec![0; decks as usize * CARD_NUMBER as usize],
+            current_card: 0,
+            dealt_cards: vec![0; CARD_NUMBER as usize],
+            opened: 0,
+            // one subtree for every bit of the number of opened cards
+            opening: vec![
+                [0; 32];
+                (u16::BITS - (decks as u16 * CARD_NUMBER as u16).leading_zeros()) as usize
+            ],
+            forfeited: false,
+            dealer_deadline: 0,
+            last_operation: 0,
+            phase: Phase::WaitingForDeck,
+            interrupted: Some(Phase::WaitingForDeck),
+            pending_operation: 0,
+            // two cards for the dealer, and two for every seat
+            pending_draws: vec![Hand::Player(0, 0); 2 + 2 * MAX_SEATS],
+        };
+        Ok(largest.try_to_vec()?.len())
+    }
+
+    /// Dealer's second card, once it is revealed.
+    pub fn dealer_hole_card(&self) -> Option<Card> {
+        match self.hole_card {
+            Some(_) => None,
+            None => self.dealer_cards.get(1).copied(),
+        }
+    }
+
+    /// Dealer's first card, visible to players.
+    pub fn dealer_up_card(&self) -> Option<Card> {
+        self.dealer_cards.first().copied()
+    }
+
+    /// Dealer's hand is a natural, which the peek did not rule out.
+    pub fn dealer_natural(&self) -> bool {
+        self.natural == Natural::Possible && is_natural(&self.dealer_cards)
+    }
+
+    /// Dealer's natural is revealed after the dealer denied it at the peek. Dealer's hand
+    /// is forfeited, and counts as busted.
+    pub fn dealer_forfeits(&self) -> bool {
+        self.natural == Natural::Denied && is_natural(&self.dealer_cards)
+    }
+
+    /// Deck position of the next card the dealer reveals: the reserved position of the hole
+    /// card when it is requested, otherwise the next card of the shoe.
+    pub fn next_position(&self) -> Option<usize> {
+        let index = match (self.pending_draws.first(), self.hole_card) {
+            (Some(Hand::Hole), Some(hole)) => hole,
+            (Some(_), _) => self.current_card,
+            (None, _) => return None,
+        };
+        self.deck_order.get(index).map(|p| *p as usize)
+    }
+
+    /// Seat taken by PLAYER.
+    pub fn seat_of(&self, player: &Pubkey) -> Option<usize> {
+        self.seats.iter().position(|s| s.player == *player)
+    }
+
+    /// Seat whose turn it is.
+    pub fn seat(&self) -> Option<&Seat> {
+        self.seats.get(self.active_seat as usize)
+    }
+
+    /// Hand the player of the active seat is playing.
+    pub fn active(&self) -> Option<&PlayerHand> {
+        self.seat().and_then(|s| s.active())
+    }
+
+    /// No stake of any seat is held in escrow.
+    pub fn escrow_empty(&self) -> bool {
+        self.seats.iter().all(|s| s.bet == 0 && s.dealer_stake == 0)
+    }
+
+    /// SEAT is dealt from the current shoe. Committed shoe is dealt only to the seats whose
+    /// seeds shuffled it, and the oracle's shoe to every seat.
+    pub fn dealt_in(&self, seat: &Seat) -> bool {
+        self.deck_source == DeckSource::Oracle || seat.seed.is_some()
+    }
+
+    /// Every seat with a bet has committed to its shuffle seed, and at least one seat did,
+    /// so the dealer can reveal its own seed. Seat without a bet is dealt from the shoe only
+    /// if it committed in time.
+    pub fn seeds_committed(&self) -> bool {
+        self.seats.iter().any(|s| s.seed_commitment.is_some())
+            && self
+                .seats
+                .iter()
+                .filter(|s| s.bet > 0)
+                .all(|s| s.seed_commitment.is_some())
+    }
+
+    /// Shoe is ready to deal from: the order of the committed deck is derived, or cards are
+    /// drawn from the oracle.
+    pub fn shuffled(&self) -> bool {
+        self.deck_source == DeckSource::Oracle || !self.deck_order.is_empty()
+    }
+
+    /// Table takes a new shoe: none is sent yet, or the current shoe failed its check,
+    /// or it is dealt up to its cut card, or it runs out during the round.
+    /// Otherwise the round is dealt from the current shoe, whose order may be known already.
+    pub fn needs_shoe(&self) -> bool {
+        let none = self.deck_source == DeckSource::Committed && self.deck_root == [0; 32];
+        none || self.forfeited || self.ended()
+    }
+
+    /// Current shoe is dealt up to its cut card, or runs out during the round.
+    pub fn ended(&self) -> bool {
+        self.current_card >= self.cut_card as usize || self.interrupted.is_some()
+    }
+
+    /// Committed shoe is ended, and the dealer still has to open all of its cards against
+    /// the commitment, before the round is paid out or a new shoe is sent.
+    pub fn shoe_unopened(&self) -> bool {
+        self.deck_source == DeckSource::Committed
+            && self.deck_root != [0; 32]
+            && !self.forfeited
+            && self.ended()
+            && self.opened < self.shoe_cards
+    }
+
+    /// Dealt round waits for the dealer: to reveal the drawn cards of its committed shoe or
+    /// to peek, to send the shoe which the round ran out of and reveal its seed, or to open
+    /// the ended shoe before the payout. Players claim the round once the dealer misses its
+    /// deadline. Steps which anyone at the table may take don't wait for the dealer.
+    pub fn waits_for_dealer(&self) -> bool {
+        let committed = self.deck_source == DeckSource::Committed;
+        let playing = self.phase != Phase::WaitingForDeck && self.phase != Phase::Settled;
+        let revealing = committed && playing && !self.pending_draws.is_empty();
+        let peeking = committed && self.phase == Phase::Peek;
+        let reshuffling = self.interrupted.is_some()
+            && (self.needs_shoe() || (committed && self.dealer_seed.is_none()));
+        self.round_unpaid() && (revealing || peeking || reshuffling || self.shoe_unopened())
+    }
+
+    /// Stakes of the dealt round are held in escrow, until the round is paid out.
+    pub fn round_unpaid(&self) -> bool {
+        self.seats.iter().any(Seat::unpaid)
+    }
+
+    /// House's cover which bets placed for the next round still wait for.
+    pub fn reserved_cover(&self) -> Option<u64> {
+        self.seats
+            .iter()
+            .filter(|s| s.bet > 0 && s.dealer_stake == 0)
+            .try_fold(0u64, |sum, s| sum.checked_add(worst_case_payout(s.bet)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::worst_case_payout;
+
+    // Seat which bet BET and INSURANCE, covered by the house, with hands of OUTCOMES.
+    fn settled_seat(bet: u64, insurance: u64, outcomes: &[Outcome]) -> Seat {
+        let mut seat = Seat::new(Pubkey::new_unique());
+        seat.bet = bet;
+        seat.dealer_stake = worst_case_payout(bet).unwrap();
+        seat.insurance = insurance;
+        let hand_bet = bet / outcomes.len() as u64;
+        seat.hands = outcomes
+            .iter()
+            .map(|&outcome| PlayerHand {
+                outcome: Some(outcome),
+                ..PlayerHand::new(hand_bet, outcomes.len() > 1)
+            })
+            .collect();
+        seat
+    }
+
+    #[test]
+    fn natural_pays_3_to_2() {
+        let seat = settled_seat(100, 0, &[Outcome::Blackjack]);
+        assert_eq!(seat.payouts(false), (250, 650));
+    }
+
+    #[test]
+    fn win_pays_1_to_1_and_push_returns_the_stake() {
+        let seat = settled_seat(200, 0, &[Outcome::PlayerWin, Outcome::Push]);
+        assert_eq!(seat.payouts(false), (300, 1500));
+        let seat = settled_seat(100, 0, &[Outcome::DealerWin]);
+        assert_eq!(seat.payouts(false), (0, 900));
+    }
+
+    #[test]
+    fn surrender_returns_half_the_stake() {
+        let seat = settled_seat(100, 0, &[Outcome::Surrender]);
+        assert_eq!(seat.payouts(false), (50, 850));
+    }
+
+    #[test]
+    fn insurance_pays_2_to_1_on_the_dealer_natural() {
+        let seat = settled_seat(100, 50, &[Outcome::DealerWin]);
+        assert_eq!(seat.payouts(true), (150, 800));
+        assert_eq!(seat.payouts(false), (0, 950));
+    }
+
+    #[test]
+    fn even_money_pays_the_natural_1_to_1() {
+        // settled as a win whatever the dealer holds
+        let mut seat = settled_seat(100, 0, &[Outcome::PlayerWin]);
+        seat.even_money = true;
+        assert_eq!(seat.payouts(true), (200, 700));
+        assert_eq!(seat.payouts(false), (200, 700));
+    }
+
+    #[test]
+    fn dealer_reveals_its_seed_once_every_seat_with_a_bet_committed() {
+        let mut table = BlackJackAccountData::new(Pubkey::new_unique(), 0, 0, 1);
+        table.seats = vec![
+            Seat::new(Pubkey::new_unique()),
+            Seat::new(Pubkey::new_unique()),
+        ];
+        assert!(!table.seeds_committed());
+        table.seats[0].bet = 100;
+        table.seats[1].seed_commitment = Some([1; 32]);
+        assert!(!table.seeds_committed());
+        table.seats[0].seed_commitment = Some([0; 32]);
+        assert!(table.seeds_committed());
+        // seat without a bet need not commit
+        table.seats[1].seed_commitment = None;
+        assert!(table.seeds_committed());
+    }
+
+    #[test]
+    fn forfeited_round_pays_the_player_the_whole_escrow() {
+        let seat = settled_seat(100, 50, &[Outcome::Forfeit]);
+        assert_eq!(seat.payouts(true), (950, 0));
+        assert_eq!(seat.payouts(false), (950, 0));
+    }
+}
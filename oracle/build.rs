@@ -0,0 +1,42 @@
+//! Program id of the mock oracle is the public key of `oracle-keypair.json`. Keypair is
+//! generated by `run.sh` for every checkout, and is never committed. Without it, as on a
+//! fresh checkout, the oracle takes the dev program id, which the program tests run with.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Program id of the oracle built without its keypair. It is never deployed.
+const DEV_ORACLE_ID: [u8; 32] = [
+    229, 111, 252, 96, 132, 56, 238, 196, 197, 35, 147, 147, 108, 113, 122, 209, 46, 239, 167,
+    195, 167, 131, 153, 229, 224, 164, 181, 65, 60, 39, 160, 70,
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed=oracle-keypair.json");
+    let id = match fs::read_to_string("oracle-keypair.json") {
+        Ok(keypair) => {
+            let bytes: Vec<u8> = keypair
+                .trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split(',')
+                .map(|b| b.trim().parse().expect("keypair holds bytes"))
+                .collect();
+            // keypair file holds the 32 bytes of the secret key, then the 32 bytes of the
+            // public key
+            assert_eq!(bytes.len(), 64, "keypair holds 64 bytes");
+            bytes[32..].to_vec()
+        }
+        Err(_) => {
+            println!(
+                "cargo:warning=oracle/oracle-keypair.json is missing, the oracle takes the dev \
+                 program id"
+            );
+            DEV_ORACLE_ID.to_vec()
+        }
+    };
+    let id = format!("pub const ORACLE_ID: [u8; 32] = {:?};\n", id);
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("oracle_id.rs");
+    fs::write(out, id).unwrap();
+}
@@ -0,0 +1,92 @@
+//! Mock randomness oracle, used by the blackjack program as an alternative deck source
+//! on a local test validator. Randomness is derived from the previous value and the
+//! clock, so it is predictable and must not be used outside of local testing. Only the
+//! requester signing for the randomness account advances it, so nobody else can draw values
+//! from it until one suits them.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint,
+    hash::hashv,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+// Program id of the mock oracle, the public key of the keypair generated next to the crate
+// by `run.sh`. Deploy with `solana program deploy --program-id oracle/oracle-keypair.json`.
+include!(concat!(env!("OUT_DIR"), "/oracle_id.rs"));
+
+/// Program id of the mock oracle.
+pub const ID: Pubkey = Pubkey::new_from_array(ORACLE_ID);
+
+/// Program id of the mock oracle.
+pub fn id() -> Pubkey {
+    ID
+}
+
+/// Randomness stored in the randomness account. Account must be owned by the oracle.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Randomness {
+    pub counter: u64,     // number of fulfilled requests
+    pub value: [u8; 32], // latest random value
+}
+
+//instruction codes.
+pub const REQUEST_RANDOMNESS: u8 = 0;
+
+/// Size of the randomness account data.
+pub const RANDOMNESS_SIZE: usize = 40;
+
+/// Instruction which stores a fresh random value into the RANDOMNESS account. Randomness
+/// account signs the request, as the program which derived its address.
+pub fn request_randomness(randomness: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        id(),
+        &[REQUEST_RANDOMNESS],
+        vec![AccountMeta::new(*randomness, true)],
+    )
+}
+
+#[cfg(not(feature = "exclude_entrypoint"))]
+entrypoint!(process_instruction);
+
+/// Logic that runs when the oracle is executed.
+///
+/// The account passed in ought to contain a `Randomness`, and sign the request.
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> entrypoint::ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let account = next_account_info(accounts_iter)?;
+    if account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    match instruction_data.first() {
+        Some(&REQUEST_RANDOMNESS) => {
+            if !account.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            let mut randomness = Randomness::deserialize(&mut &account.data.borrow()[..])?;
+            let clock = Clock::get()?;
+            randomness.counter += 1;
+            randomness.value = hashv(&[
+                &randomness.value,
+                &clock.slot.to_le_bytes(),
+                &clock.unix_timestamp.to_le_bytes(),
+                &randomness.counter.to_le_bytes(),
+            ])
+            .to_bytes();
+            randomness.serialize(&mut &mut account.data.borrow_mut()[..])?;
+            msg!("Randomness fulfilled: {:?}", randomness);
+            Ok(())
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
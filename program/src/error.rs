@@ -0,0 +1,111 @@
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive as _;
+use solana_program::decode_error::DecodeError;
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+// Errors returned by the program, as `ProgramError::Custom` with the variant's index.
+// Clients decode the code back with `TryFrom<u32>`.
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum BlackJackError {
+    #[error("Instruction is empty or unknown")]
+    InvalidInstruction,
+    #[error("Instruction data can't be deserialized")]
+    DeserializationError,
+    #[error("Account serialization error")]
+    AccountSerializationError,
+    #[error("Instruction is not signed by the table's dealer or player")]
+    NotAuthorized,
+    #[error("Instruction is not allowed in the current phase of the game")]
+    InvalidPhase,
+    #[error("All seats at the table are taken")]
+    SeatTaken,
+    #[error("Unknown randomness oracle")]
+    UnknownOracle,
+    #[error("Randomness account is not the table's account owned by the oracle")]
+    InvalidRandomnessAccount,
+    #[error("Player seed is already committed for this deck")]
+    SeedAlreadyCommitted,
+    #[error("Seed is revealed only once it is committed, and the dealer's once every seat with a bet committed")]
+    SeedNotCommitted,
+    #[error("Revealed seed does not match the commitment")]
+    SeedMismatch,
+    #[error("Deck is not shuffled yet, both seeds must be revealed")]
+    DeckNotShuffled,
+    #[error("Cards of the oracle deck are dealt by the program")]
+    OracleDeck,
+    #[error("No card is waiting to be revealed")]
+    NoPendingCard,
+    #[error("Revealed card does not match the deck commitment")]
+    CardMismatch,
+    #[error("Bet must be greater than zero")]
+    InvalidBet,
+    #[error("Stakes of the previous round must be paid out first")]
+    EscrowNotEmpty,
+    #[error("Account does not belong to the table's dealer or player")]
+    WrongAccount,
+    #[error("Token account does not hold the table's mint, or has the wrong owner")]
+    InvalidTokenAccount,
+    #[error("House vault can't cover the worst case of the bet")]
+    InsufficientBankroll,
+    #[error("Player can double only on its first two cards, with a total allowed by the rules")]
+    DoubleNotAllowed,
+    #[error("Player can split only a pair of its hand, up to the hands allowed by the rules")]
+    SplitNotAllowed,
+    #[error("Insurance is at most half the stake under an ace, and even money only on a natural")]
+    InvalidInsurance,
+    #[error("Player can surrender only as the first decision on its hand, if the rules allow it")]
+    SurrenderNotAllowed,
+    #[error("Shoe holds from 1 to 8 decks the table is sized for, and the cut card lies within it")]
+    InvalidRules,
+    #[error("Deck does not have the size or the composition of the table's shoe")]
+    InvalidDeck,
+    #[error("Player can act only on the turn of its seat")]
+    NotPlayersTurn,
+    #[error("Table account is not at the address derived from the dealer and the table index")]
+    InvalidTableAddress,
+    #[error("Cards of the committed deck are revealed by the dealer")]
+    CommittedDeck,
+    #[error("Dealer must open the ended shoe first")]
+    ShoeNotOpened,
+    #[error("Shoe failed its check or the dealer missed its deadline, and is not dealt from again")]
+    ShoeForfeited,
+    #[error("Deadline of the seats or the dealer has not passed yet")]
+    DeadlineNotPassed,
+}
+
+impl From<BlackJackError> for ProgramError {
+    fn from(e: BlackJackError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for BlackJackError {
+    fn type_of() -> &'static str {
+        "BlackJackError"
+    }
+}
+
+impl TryFrom<u32> for BlackJackError {
+    type Error = u32;
+
+    /// Error of the custom CODE returned by the program. Unknown code is returned back.
+    fn try_from(code: u32) -> Result<Self, u32> {
+        BlackJackError::from_u32(code).ok_or(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_error_is_decoded_from_its_code() {
+        let last = BlackJackError::DeadlineNotPassed as u32;
+        for code in 0..=last {
+            assert_eq!(BlackJackError::try_from(code).map(|e| e as u32), Ok(code));
+        }
+        assert_eq!(BlackJackError::try_from(last + 1), Err(last + 1));
+    }
+}
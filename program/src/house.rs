@@ -0,0 +1,37 @@
+use solana_program::account_info::AccountInfo;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::sysvar::Sysvar;
+
+use black_jack_core::house::house_address;
+
+use crate::error::BlackJackError;
+
+// House bankroll. The dealer funds the house vault, an account owned by the program
+// at an address derived from the table. Covers of the bets are taken from the vault,
+// and lost stakes are paid back into it. When the table is configured with a mint,
+// the bankroll is held by a token account of the house vault instead.
+
+/// Check that HOUSE is the house vault of the TABLE account. Returns its bump seed.
+pub fn check_house(
+    house: &AccountInfo,
+    table: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<u8, ProgramError> {
+    let (expected, bump) = house_address(table, program_id);
+    if *house.key != expected {
+        return Err(BlackJackError::WrongAccount.into());
+    }
+    Ok(bump)
+}
+
+/// Lamports of the HOUSE vault which can be staked: all above its rent exemption.
+/// Vault which is not created by the program yet holds none.
+pub fn lamport_bankroll(house: &AccountInfo, program_id: &Pubkey) -> Result<u64, ProgramError> {
+    if house.owner != program_id {
+        return Ok(0);
+    }
+    let rent = Rent::get()?.minimum_balance(house.data_len());
+    Ok(house.lamports().saturating_sub(rent))
+}
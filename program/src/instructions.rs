@@ -1,265 +1,2696 @@
     use borsh::{BorshDeserialize, BorshSerialize};
-    use solana_program::account_info::AccountInfo;
+    use solana_program::account_info::{next_account_info, AccountInfo};
+    use solana_program::clock::Clock;
+    use solana_program::entrypoint::ProgramResult;
+    use solana_program::instruction::{AccountMeta, Instruction};
     use solana_program::msg;
+    use solana_program::program::invoke;
+    use solana_program::pubkey::Pubkey;
+    use solana_program::system_instruction;
+    use solana_program::sysvar::Sysvar;
 
-    #[derive(BorshSerialize, BorshDeserialize)]
+    use black_jack_core::card::{card_at, hand_value, is_natural, Card, Rank};
+    use black_jack_core::commitment::{
+        leaf_hash, push_leaf, seed_hash, subtrees_root, verify_proof,
+    };
+    use black_jack_core::constants::*;
+    use black_jack_core::house::HOUSE_SEED;
+    use black_jack_core::oracle::ORACLE_SEED;
+    use black_jack_core::state::{
+        BlackJackAccountData, DeckSource, Hand, Natural, Outcome, Phase, PlayerHand, Seat,
+        SurrenderRule, TableRules,
+    };
+    use crate::error::BlackJackError;
+    use crate::house::{check_house, lamport_bankroll};
+    use crate::pda::create_pda_account;
+    use crate::oracle::{check_randomness, request_random};
+    use crate::shuffle::{deck_order, pick};
+    use crate::token::{
+        check_token_account, check_vault, deposit, token_balance, transfer_signed,
+    };
+
+    /// New table of the dealer, with room for the cards of the given number of decks.
+    /// Dealer can run many tables, told apart by their index.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct CreateTable {
+        pub index: u16,
+        pub decks: u8,
+    }
+
+    /// Commitment to the shuffled deck. Cards themselves stay with the dealer. Merkle tree
+    /// is built over the positions of the table's shoe, which the proofs of revealed cards
+    /// are checked against.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
     pub struct SendDeck {
-        pub deck: Vec<u8>,
+        pub deck_root: [u8; 32],       // Merkle root over salted cards
+        pub seed_commitment: [u8; 32], // hash of the dealer's shuffle seed
+    }
+
+    /// Player's stake for the next round, in lamports, or in tokens of the table's mint.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct PlaceBet {
+        pub amount: u64,
+    }
+
+    /// Player's insurance against the dealer's natural, when the dealer shows an ace.
+    /// Amount is a side bet of up to half the stake, 0 declines the insurance. Player with
+    /// a natural can take even money instead, and is paid 1:1 whatever the dealer holds.
+    /// Under a ten, no insurance is taken, and 0 declines the early surrender.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct Insurance {
+        pub amount: u64,
+        pub even_money: bool,
+    }
+
+    /// Lamports, or tokens of the table's mint, moved between the dealer and the house vault.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct HouseTransfer {
+        pub amount: u64,
+    }
+
+    /// Player's commitment to its shuffle seed.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct CommitSeed {
+        pub seed_commitment: [u8; 32],
+    }
+
+    /// Shuffle seed, revealed after both seeds are committed.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct RevealSeed {
+        pub seed: [u8; 32],
+    }
+
+    /// Card revealed by the dealer, with the salt and Merkle proof for its deck position.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct RevealedCard {
+        pub card: Card,
+        pub salt: [u8; 32],
+        pub proof: Vec<[u8; 32]>,
+    }
+
+    /// Card of the ended shoe opened by the dealer, with the salt of its deck position.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub struct OpenedCard {
+        pub card: Card,
+        pub salt: [u8; 32],
+    }
+
+    /// Instruction of the program, with its payload. Both the program and the clients
+    /// serialize it with Borsh, so the layout of the instruction data is defined only here.
+    #[derive(BorshSerialize, BorshDeserialize, Debug)]
+    pub enum BlackJackInstruction {
+        SendDeck(SendDeck),
+        Deal,
+        ClearData,
+        PlayerHit,
+        PlayerStand,
+        RevealCard(RevealedCard),
+        PlayerCommitSeed(CommitSeed),
+        PlayerRevealSeed(RevealSeed),
+        DealerRevealSeed(RevealSeed),
+        RequestOracleDeck,
+        JoinTable,
+        SetRules(TableRules),
+        DealerPlay,
+        PlaceBet(PlaceBet),
+        Payout,
+        SetMint(Option<Pubkey>), // None stakes lamports
+        HouseDeposit(HouseTransfer),
+        HouseWithdraw(HouseTransfer),
+        PlayerDouble,
+        PlayerSplit,
+        Insurance(Insurance),
+        PlayerSurrender,
+        CreateTable(CreateTable),
+        DealerPeek(bool), // true when the hole card makes a natural
+        DrawOracleCard,
+        OpenShoe(Vec<OpenedCard>), // next cards of the ended shoe, in deck order
+        WithdrawBet,
+        DropSeeds,
+        ClaimForfeit,
     }
 
-    // The type of state managed by this program. The type defined here
-    // must match the `BlackJackAccountData` type defined by the client.
+    impl BlackJackInstruction {
+        /// Code of the operation, recorded as the last operation of the table.
+        pub fn operation(&self) -> u8 {
+            match self {
+                BlackJackInstruction::SendDeck(_) => SEND_DECK,
+                BlackJackInstruction::Deal => DEAL,
+                BlackJackInstruction::ClearData => CLEAR_DATA,
+                BlackJackInstruction::PlayerHit => PLAYER_HIT,
+                BlackJackInstruction::PlayerStand => PLAYER_STAND,
+                BlackJackInstruction::RevealCard(_) => REVEAL_CARD,
+                BlackJackInstruction::PlayerCommitSeed(_) => PLAYER_COMMIT_SEED,
+                BlackJackInstruction::PlayerRevealSeed(_) => PLAYER_REVEAL_SEED,
+                BlackJackInstruction::DealerRevealSeed(_) => DEALER_REVEAL_SEED,
+                BlackJackInstruction::RequestOracleDeck => REQUEST_ORACLE_DECK,
+                BlackJackInstruction::JoinTable => JOIN_TABLE,
+                BlackJackInstruction::SetRules(_) => SET_RULES,
+                BlackJackInstruction::DealerPlay => DEALER_PLAY,
+                BlackJackInstruction::PlaceBet(_) => PLACE_BET,
+                BlackJackInstruction::Payout => PAYOUT,
+                BlackJackInstruction::SetMint(_) => SET_MINT,
+                BlackJackInstruction::HouseDeposit(_) => HOUSE_DEPOSIT,
+                BlackJackInstruction::HouseWithdraw(_) => HOUSE_WITHDRAW,
+                BlackJackInstruction::PlayerDouble => PLAYER_DOUBLE,
+                BlackJackInstruction::PlayerSplit => PLAYER_SPLIT,
+                BlackJackInstruction::Insurance(_) => INSURANCE,
+                BlackJackInstruction::PlayerSurrender => PLAYER_SURRENDER,
+                BlackJackInstruction::CreateTable(_) => CREATE_TABLE,
+                BlackJackInstruction::DealerPeek(_) => DEALER_PEEK,
+                BlackJackInstruction::DrawOracleCard => DRAW_ORACLE_CARD,
+                BlackJackInstruction::OpenShoe(_) => OPEN_SHOE,
+                BlackJackInstruction::WithdrawBet => WITHDRAW_BET,
+                BlackJackInstruction::DropSeeds => DROP_SEEDS,
+                BlackJackInstruction::ClaimForfeit => CLAIM_FORFEIT,
+            }
+        }
+
+        /// Name of the instruction, without its payload, which is logged.
+        pub fn name(&self) -> &'static str {
+            match self {
+                BlackJackInstruction::SendDeck(_) => "SendDeck",
+                BlackJackInstruction::Deal => "Deal",
+                BlackJackInstruction::ClearData => "ClearData",
+                BlackJackInstruction::PlayerHit => "PlayerHit",
+                BlackJackInstruction::PlayerStand => "PlayerStand",
+                BlackJackInstruction::RevealCard(_) => "RevealCard",
+                BlackJackInstruction::PlayerCommitSeed(_) => "PlayerCommitSeed",
+                BlackJackInstruction::PlayerRevealSeed(_) => "PlayerRevealSeed",
+                BlackJackInstruction::DealerRevealSeed(_) => "DealerRevealSeed",
+                BlackJackInstruction::RequestOracleDeck => "RequestOracleDeck",
+                BlackJackInstruction::JoinTable => "JoinTable",
+                BlackJackInstruction::SetRules(_) => "SetRules",
+                BlackJackInstruction::DealerPlay => "DealerPlay",
+                BlackJackInstruction::PlaceBet(_) => "PlaceBet",
+                BlackJackInstruction::Payout => "Payout",
+                BlackJackInstruction::SetMint(_) => "SetMint",
+                BlackJackInstruction::HouseDeposit(_) => "HouseDeposit",
+                BlackJackInstruction::HouseWithdraw(_) => "HouseWithdraw",
+                BlackJackInstruction::PlayerDouble => "PlayerDouble",
+                BlackJackInstruction::PlayerSplit => "PlayerSplit",
+                BlackJackInstruction::Insurance(_) => "Insurance",
+                BlackJackInstruction::PlayerSurrender => "PlayerSurrender",
+                BlackJackInstruction::CreateTable(_) => "CreateTable",
+                BlackJackInstruction::DealerPeek(_) => "DealerPeek",
+                BlackJackInstruction::DrawOracleCard => "DrawOracleCard",
+                BlackJackInstruction::OpenShoe(_) => "OpenShoe",
+                BlackJackInstruction::WithdrawBet => "WithdrawBet",
+                BlackJackInstruction::DropSeeds => "DropSeeds",
+                BlackJackInstruction::ClaimForfeit => "ClaimForfeit",
+            }
+        }
+
+        /// Instruction on the TABLE account, signed by SIGNER, the dealer or a player.
+        /// ACCOUNTS follow the signer, as the instruction requires them.
+        pub fn instruction(
+            &self,
+            program_id: &Pubkey,
+            table: &Pubkey,
+            signer: &Pubkey,
+            accounts: Vec<AccountMeta>,
+        ) -> std::io::Result<Instruction> {
+            let mut metas = vec![AccountMeta::new(*table, false), AccountMeta::new(*signer, true)];
+            metas.extend(accounts);
+            Ok(Instruction::new_with_bytes(*program_id, &self.try_to_vec()?, metas))
+        }
+
+        /// Instruction decoded from DATA. Data without a known instruction tag is an invalid
+        /// instruction, and a known instruction with malformed arguments fails to deserialize.
+        pub fn unpack(data: &[u8]) -> Result<Self, BlackJackError> {
+            if data.is_empty() {
+                return Err(BlackJackError::InvalidInstruction);
+            }
+            Self::try_from_slice(data).map_err(|e| {
+                // borsh reports a tag which names no variant of the enum this way
+                if e.to_string().starts_with("Unexpected variant index") {
+                    BlackJackError::InvalidInstruction
+                } else {
+                    BlackJackError::DeserializationError
+                }
+            })
+        }
+    }
+
+    /// Transitions of the round, played by the program on the state of the table.
+    trait Round {
+        fn give_dealer(&mut self, card: Card);
+        fn give_player(&mut self, seat: u8, index: u8, card: Card);
+        fn peek(&mut self);
+        fn after_peek(&mut self);
+        fn next_turn(&mut self, from: usize);
+        fn dealer_turn(&mut self);
+        fn finish_hand(&mut self);
+        fn give_next(&mut self, card: Card);
+        fn reserve_hole(&mut self);
+        fn oracle_weights(&self) -> Vec<u64>;
+        fn finish_draws(&mut self);
+        fn play_dealer(&mut self);
+        fn settle(&mut self);
+        fn request_cards(&mut self, operation: u8, hands: &[Hand]);
+        fn draw_pending(&mut self);
+        fn new_shoe(&mut self, source: DeckSource, operation: u8);
+        fn resume(&mut self);
+        fn derive_order(&mut self);
+        fn drop_seeds(&mut self);
+        fn forfeit_round(&mut self);
+        fn open_shoe(&mut self, cards: &[OpenedCard]) -> Result<(), BlackJackError>;
+        fn deal(&mut self, stakes: &[u64]);
+        fn double(&mut self) -> Result<u64, BlackJackError>;
+        fn split(&mut self) -> Result<u64, BlackJackError>;
+        fn insure(&mut self, amount: u64, even_money: bool) -> Result<u64, BlackJackError>;
+        fn surrender(&mut self) -> Result<(), BlackJackError>;
+        fn dealer_peek(&mut self, natural: bool);
+    }
+
+    impl Round for BlackJackAccountData {
+        fn give_dealer(&mut self, card: Card) {
+            self.dealer_cards.push(card);
+            self.dealer_hand = hand_value(&self.dealer_cards).0;
+        }
+
+        fn give_player(&mut self, seat: u8, index: u8, card: Card) {
+            let hand = &mut self.seats[seat as usize].hands[index as usize];
+            hand.cards.push(card);
+            hand.total = hand_value(&hand.cards).0;
+        }
+
+        /// Dealer peeks at the hole card when it shows an ace or a ten, the only up cards
+        /// of a natural. Dealer of a committed deck tells if it has a natural, and the oracle's
+        /// draw of the hole card tells it for the oracle's shoe. Only a natural is revealed.
+        fn peek(&mut self) {
+            let ten_or_ace = self
+                .dealer_up_card()
+                .is_some_and(|c| c.rank == Rank::Ace || c.rank.value() == 10);
+            if !ten_or_ace {
+                return self.after_peek();
+            }
+            self.phase = Phase::Peek;
+            if self.deck_source == DeckSource::Oracle {
+                self.request_cards(DEALER_PEEK, &[Hand::Hole]);
+            }
+        }
+
+        /// Revealed natural settles the round right away. Otherwise the hole card stays
+        /// face down, and the seats take their turns.
+        fn after_peek(&mut self) {
+            if self.dealer_natural() {
+                msg!("Dealer has a natural, round is settled");
+                self.settle();
+            } else {
+                self.phase = Phase::PlayerTurn;
+                self.next_turn(0);
+            }
+        }
+
+        /// Pass the turn to the first seat from FROM which takes it, in the order of the seats.
+        /// Every dealt seat decides on the insurance, and every live seat plays its hands.
+        /// After the last seat, the dealer peeks at the hole card, or plays its hand.
+        fn next_turn(&mut self, from: usize) {
+            let insurance = self.phase == Phase::Insurance;
+            let next = (from..self.seats.len()).find(|&i| {
+                let seat = &self.seats[i];
+                if insurance {
+                    seat.hands.first().is_some_and(|h| h.outcome.is_none())
+                } else {
+                    seat.live()
+                }
+            });
+            match next {
+                Some(i) => self.active_seat = i as u8,
+                None if insurance => self.peek(),
+                None => self.dealer_turn(),
+            }
+        }
+
+        /// Turn passes to the dealer once every seat is played. If no seat is left with a hand
+        /// against the dealer's hand, the dealer only reveals the hole card, which decides
+        /// the insurance and the naturals, and the round is settled.
+        fn dealer_turn(&mut self) {
+            if self.seats.iter().any(|s| s.live()) {
+                self.phase = Phase::DealerTurn;
+            } else {
+                self.play_dealer();
+            }
+        }
+
+        /// Move to the next hand of the active seat. After its last hand, the turn passes
+        /// to the next seat.
+        fn finish_hand(&mut self) {
+            let index = self.active_seat as usize;
+            let seat = &mut self.seats[index];
+            seat.active_hand += 1;
+            if seat.active_hand as usize >= seat.hands.len() {
+                self.next_turn(index + 1);
+            }
+        }
+
+        /// Give CARD to the first hand waiting for a card. Hole card is revealed from its
+        /// reserved position, other cards from the next position of the shoe.
+        fn give_next(&mut self, card: Card) {
+            match self.pending_draws.remove(0) {
+                Hand::Player(seat, index) => {
+                    self.current_card += 1;
+                    self.give_player(seat, index, card);
+                }
+                Hand::Dealer => {
+                    self.current_card += 1;
+                    self.give_dealer(card);
+                }
+                Hand::Hole => {
+                    self.hole_card = None;
+                    self.give_dealer(card);
+                }
+            }
+            self.reserve_hole();
+            if self.pending_draws.is_empty() {
+                self.finish_draws();
+            }
+        }
+
+        /// Deal the hole card face down, when it is the next card to give. Its position
+        /// of the shoe is reserved, and the card is revealed from there later.
+        fn reserve_hole(&mut self) {
+            if self.hole_card.is_none() && self.pending_draws.first() == Some(&Hand::Hole) {
+                self.pending_draws.remove(0);
+                self.hole_card = Some(self.current_card);
+                self.current_card += 1;
+            }
+        }
+
+        /// Chances of every card to be drawn next from the oracle's shoe: how many of it
+        /// are left. Once the peek told the reserved hole card makes no natural, the hole card
+        /// is drawn from the cards which make none, and the other cards with the chances left
+        /// by such a hole card.
+        fn oracle_weights(&self) -> Vec<u64> {
+            let decks = (self.shoe_cards / CARD_NUMBER as u16) as u64;
+            let left: Vec<u64> = self.dealt_cards.iter().map(|&d| decks - d as u64).collect();
+            if self.natural != Natural::Denied || self.hole_card.is_none() {
+                return left;
+            }
+            let up_card = self.dealer_up_card();
+            let natural = |i: usize| up_card.is_some_and(|up| is_natural(&[up, card_at(i)]));
+            let total: u64 = left.iter().sum();
+            let naturals: u64 = (0..left.len()).filter(|&i| natural(i)).map(|i| left[i]).sum();
+            let others = total - naturals;
+            let hole = self.pending_draws.first() == Some(&Hand::Hole);
+            left.iter()
+                .enumerate()
+                .map(|(i, &count)| match (hole, natural(i)) {
+                    (true, true) => 0,
+                    (true, false) => count,
+                    (false, true) => count * others,
+                    // without a card for the hole, no card is left to draw
+                    (false, false) => count * others.saturating_sub(1),
+                })
+                .collect()
+        }
+
+        /// Once all requested cards are given, the operation which requested them becomes
+        /// the last operation, and the round goes on.
+        fn finish_draws(&mut self) {
+            self.last_operation = self.pending_operation;
+            let busted = self.active().is_some_and(|h| h.total > 21);
+            if self.pending_operation == DEAL {
+                // insurance is decided before the dealer peeks under an ace, and early
+                // surrender under an ace or a ten
+                let up_card = self.dealer_up_card();
+                let ace = up_card.is_some_and(|c| c.rank == Rank::Ace);
+                let ten = up_card.is_some_and(|c| c.rank.value() == 10);
+                if ace || (ten && self.rules.surrender == SurrenderRule::Early) {
+                    self.phase = Phase::Insurance;
+                    self.next_turn(0);
+                } else {
+                    self.peek();
+                }
+            } else if (self.pending_operation == PLAYER_HIT
+                || self.pending_operation == PLAYER_DOUBLE)
+                && busted
+            {
+                self.last_operation = PLAYER_BUSTED;
+                self.finish_hand();
+            } else if self.pending_operation == PLAYER_DOUBLE {
+                // doubled hand takes exactly one card
+                self.finish_hand();
+            } else if self.pending_operation == PLAYER_SPLIT {
+                let aces = self.active().is_some_and(|h| h.cards[0].rank == Rank::Ace);
+                if aces && self.rules.split_aces_one_card {
+                    // both split aces are finished with their one card
+                    self.seats[self.active_seat as usize].active_hand += 1;
+                    self.finish_hand();
+                }
+            } else if self.pending_operation == DEALER_PEEK {
+                self.after_peek();
+            } else if self.pending_operation == DEALER_PLAY {
+                self.play_dealer();
+            }
+        }
+
+        /// Play the dealer's hand by the table rules, once the hole card is revealed: hit
+        /// below 17, and on soft 17 if the rules say so. Each requested card continues the
+        /// play once it is revealed. Standing or busting settles the round. Dealer only
+        /// stands once no seat is left with a hand against its hand.
+        fn play_dealer(&mut self) {
+            if self.hole_card.is_some() {
+                self.request_cards(DEALER_PLAY, &[Hand::Hole]);
+                return;
+            }
+            let (total, soft) = hand_value(&self.dealer_cards);
+            let live = self.seats.iter().any(|s| s.live());
+            if self.dealer_forfeits() {
+                msg!("Dealer denied the natural at the peek, its hand is forfeited");
+                self.last_operation = DEALER_BUSTED;
+                self.settle();
+            } else if total > 21 {
+                self.last_operation = DEALER_BUSTED;
+                self.settle();
+            } else if live && (total < 17 || (total == 17 && soft && self.rules.hit_soft_17)) {
+                self.request_cards(DEALER_PLAY, &[Hand::Dealer]);
+            } else {
+                self.last_operation = DEALER_STAND;
+                self.settle();
+            }
+        }
+
+        /// Decide the outcome of every seat's hands against the dealer's hand, and end
+        /// the round. Busted hand loses even if the dealer busts too. A natural beats
+        /// any other hand. Surrendered hands are already decided. Dealer's natural whose
+        /// hole card was dealt again after the peek counts as a bust.
+        fn settle(&mut self) {
+            let dealer_natural = self.dealer_natural();
+            let forfeit = self.dealer_forfeits();
+            let dealer_hand = self.dealer_hand;
+            for seat in self.seats.iter_mut() {
+                let even_money = seat.even_money;
+                for hand in seat.hands.iter_mut().filter(|h| h.outcome.is_none()) {
+                    let player_natural = hand.is_natural();
+                    let outcome = if even_money {
+                        Outcome::PlayerWin
+                    } else if hand.total > 21 {
+                        Outcome::DealerWin
+                    } else if player_natural && dealer_natural {
+                        Outcome::Push
+                    } else if player_natural {
+                        Outcome::Blackjack
+                    } else if dealer_natural {
+                        Outcome::DealerWin
+                    } else if forfeit || dealer_hand > 21 || hand.total > dealer_hand {
+                        Outcome::PlayerWin
+                    } else if hand.total < dealer_hand {
+                        Outcome::DealerWin
+                    } else {
+                        Outcome::Push
+                    };
+                    msg!("Hand settled: {:?}", outcome);
+                    hand.outcome = Some(outcome);
+                }
+            }
+            self.phase = Phase::Settled;
+        }
+
+        /// Request cards for HANDS. OPERATION becomes the last operation
+        /// once the dealer reveals all of them. If there are not enough cards left,
+        /// the round is interrupted with its hands and requested cards, and a new shoe
+        /// is requested. Revealed hole card is already dealt from the shoe.
+        fn request_cards(&mut self, operation: u8, hands: &[Hand]) {
+            self.pending_operation = operation;
+            self.pending_draws.extend_from_slice(hands);
+            let reserved = self.hole_card.is_some();
+            let needed = self
+                .pending_draws
+                .iter()
+                .filter(|&&h| !(reserved && h == Hand::Hole))
+                .count();
+            if self.current_card + needed > self.shoe_cards as usize {
+                msg!("No cards left to deal, round waits for a new shoe");
+                self.interrupted = Some(self.phase);
+                self.phase = Phase::WaitingForDeck;
+                self.last_operation = REQUEST_NEW_DECK;
+                return;
+            }
+            self.draw_pending();
+        }
+
+        /// Wait for the dealer to reveal the requested cards, or for the cards of the oracle's
+        /// shoe to be drawn.
+        fn draw_pending(&mut self) {
+            self.last_operation = AWAITING_REVEAL;
+            self.reserve_hole();
+        }
+
+        /// Replace the shoe with a new one from SOURCE, received with OPERATION. The round
+        /// interrupted by the end of the previous shoe keeps its hands and requested cards,
+        /// otherwise the table waits for the next round.
+        fn new_shoe(&mut self, source: DeckSource, operation: u8) {
+            self.last_operation = operation;
+            self.phase = Phase::WaitingForDeck;
+            self.deck_source = source;
+            self.deck_root = [0; 32];
+            self.shoe_cards = self.rules.shoe_cards();
+            self.cut_card = self.rules.cut_card;
+            self.dealer_seed_commitment = [0; 32];
+            self.dealer_seed = None;
+            for seat in self.seats.iter_mut() {
+                seat.seed_commitment = None;
+                seat.seed = None;
+            }
+            self.deck_order.clear();
+            self.current_card = 0;
+            self.dealt_cards = vec![0; CARD_NUMBER as usize];
+            self.opened = 0;
+            self.opening.clear();
+            self.forfeited = false;
+            if self.interrupted.is_some() && self.hole_card.take().is_some() {
+                // hole card of the previous shoe is dealt again from the new one
+                self.pending_draws.insert(0, Hand::Hole);
+                self.natural = Natural::Excluded;
+            }
+            if self.interrupted.is_none() {
+                self.hole_card = None;
+                self.natural = Natural::Possible;
+                self.dealer_hand = 0;
+                self.dealer_cards.clear();
+                for seat in self.seats.iter_mut() {
+                    seat.hands.clear();
+                    seat.active_hand = 0;
+                }
+                self.active_seat = 0;
+                self.pending_operation = operation;
+                self.pending_draws.clear();
+            }
+        }
+
+        /// Resume the interrupted round once the new shoe is shuffled, by dealing
+        /// the cards it waits for.
+        fn resume(&mut self) {
+            if let Some(phase) = self.interrupted.take() {
+                msg!("Round resumed with the new shoe");
+                self.phase = phase;
+                self.draw_pending();
+            }
+        }
+
+        /// Derive the order of the committed shoe from the dealer's seed and the seeds of all
+        /// committed seats, once every one of them is revealed. The interrupted round resumes
+        /// with the shuffled shoe.
+        fn derive_order(&mut self) {
+            let player_seeds: Option<Vec<[u8; 32]>> = self
+                .seats
+                .iter()
+                .filter(|s| s.seed_commitment.is_some())
+                .map(|s| s.seed)
+                .collect();
+            if let (Some(dealer_seed), Some(player_seeds)) = (self.dealer_seed, player_seeds) {
+                if self.deck_order.is_empty() {
+                    let shoe_cards = self.shoe_cards as usize;
+                    self.deck_order = deck_order(&dealer_seed, &player_seeds, shoe_cards);
+                }
+            }
+            if !self.deck_order.is_empty() {
+                self.resume();
+            }
+        }
+
+        /// Drop the seats which did not reveal their seeds in time from the shuffle. They are
+        /// not dealt from the shoe, and its order is derived from the revealed seeds.
+        fn drop_seeds(&mut self) {
+            for seat in self.seats.iter_mut().filter(|s| s.seed.is_none()) {
+                seat.seed_commitment = None;
+            }
+            self.last_operation = DROP_SEEDS;
+            self.derive_order();
+        }
+
+        /// End the round dealt from a shoe which failed its check, or whose dealer missed its
+        /// deadline. Every hand in escrow is
+        /// forfeited, and the payout gives its player the whole escrow, with the house's
+        /// cover. The shoe is not dealt from again, and the dealer must send a new one once
+        /// the round is paid out.
+        fn forfeit_round(&mut self) {
+            for seat in self.seats.iter_mut().filter(|s| s.unpaid()) {
+                for hand in seat.hands.iter_mut() {
+                    hand.outcome = Some(Outcome::Forfeit);
+                }
+            }
+            self.forfeited = true;
+            self.interrupted = None;
+            self.pending_draws.clear();
+            self.deck_order.clear();
+            self.phase = Phase::Settled;
+            self.last_operation = SHOE_FORFEIT;
+        }
+
+        /// Open the next CARDS of the ended shoe, in deck order. Once every card is opened,
+        /// the tree over them must have the root of the commitment, and the shoe must hold
+        /// every card once for each of its decks. Otherwise the round is forfeited.
+        fn open_shoe(&mut self, cards: &[OpenedCard]) -> Result<(), BlackJackError> {
+            if self.opened as usize + cards.len() > self.shoe_cards as usize {
+                return Err(BlackJackError::InvalidDeck);
+            }
+            if self.opened == 0 {
+                // cards are counted again, over the whole shoe
+                self.dealt_cards = vec![0; CARD_NUMBER as usize];
+                self.opening.clear();
+            }
+            for opened in cards {
+                let position = self.opened as usize;
+                push_leaf(
+                    &mut self.opening,
+                    position,
+                    leaf_hash(position, &opened.card, &opened.salt),
+                );
+                if let Some(count) = self.dealt_cards.get_mut(opened.card.index()) {
+                    *count = count.saturating_add(1);
+                }
+                self.opened += 1;
+            }
+            self.last_operation = OPEN_SHOE;
+            if self.opened < self.shoe_cards {
+                return Ok(());
+            }
+            let decks = self.shoe_cards / CARD_NUMBER as u16;
+            if subtrees_root(&self.opening) != Some(self.deck_root) {
+                msg!("Opened shoe does not match its commitment, round is forfeited");
+                self.forfeit_round();
+            } else if self.dealt_cards.iter().any(|&count| count as u16 != decks) {
+                msg!("Opened shoe is stacked, round is forfeited");
+                self.forfeit_round();
+            } else {
+                msg!("Shoe is opened, and matches its commitment");
+            }
+            Ok(())
+        }
+
+        /// Deal a new round to the seats dealt in from the shoe, each covered by the house
+        /// with its STAKE. Dealer's up card and hole card are requested first, and then two
+        /// cards for every dealt seat, in their order.
+        fn deal(&mut self, stakes: &[u64]) {
+            let dealt: Vec<bool> = self.seats.iter().map(|s| self.dealt_in(s)).collect();
+            self.dealer_hand = 0;
+            self.dealer_cards.clear();
+            self.hole_card = None;
+            self.natural = Natural::Possible;
+            let mut draws = vec![Hand::Dealer, Hand::Hole];
+            for (i, seat) in self.seats.iter_mut().enumerate() {
+                seat.active_hand = 0;
+                if !dealt[i] {
+                    // seat whose seed is not in the shoe waits for the next shoe
+                    seat.hands.clear();
+                    continue;
+                }
+                seat.dealer_stake = stakes[i];
+                seat.hands = vec![PlayerHand::new(seat.bet, false)];
+                seat.insurance = 0;
+                seat.even_money = false;
+                draws.extend_from_slice(&[Hand::Player(i as u8, 0), Hand::Player(i as u8, 0)]);
+            }
+            self.active_seat = 0;
+            self.pending_draws.clear();
+            self.phase = Phase::Dealt;
+            self.request_cards(DEAL, &draws);
+        }
+
+        /// Double the bet of the active hand, and request exactly one more card for it.
+        /// Returns the added stake, which the player moves into the escrow.
+        fn double(&mut self) -> Result<u64, BlackJackError> {
+            let index = self.active_seat as usize;
+            let (active, bet) = match self.active() {
+                Some(hand)
+                    if hand.cards.len() == 2 && self.rules.double_on.allows(hand.total) =>
+                {
+                    (self.seats[index].active_hand, hand.bet)
+                }
+                _ => return Err(BlackJackError::DoubleNotAllowed),
+            };
+            let seat = &mut self.seats[index];
+            if seat.bet + bet > seat.dealer_stake {
+                return Err(BlackJackError::InsufficientBankroll);
+            }
+            seat.hands[active as usize].bet += bet;
+            seat.bet += bet;
+            self.request_cards(PLAYER_DOUBLE, &[Hand::Player(index as u8, active)]);
+            Ok(bet)
+        }
+
+        /// Split the pair of the active hand into two hands, each with the bet of the pair,
+        /// and request one more card for each of them. Returns the added stake, which
+        /// the player moves into the escrow.
+        fn split(&mut self) -> Result<u64, BlackJackError> {
+            let max_hands = if self.rules.resplit {
+                MAX_SPLIT_HANDS as usize
+            } else {
+                2
+            };
+            let index = self.active_seat as usize;
+            let (active, bet) = match self.active() {
+                Some(hand)
+                    if hand.cards.len() == 2
+                        && hand.cards[0].rank == hand.cards[1].rank
+                        && self.seats[index].hands.len() < max_hands =>
+                {
+                    (self.seats[index].active_hand, hand.bet)
+                }
+                _ => return Err(BlackJackError::SplitNotAllowed),
+            };
+            let seat = &mut self.seats[index];
+            if seat.bet + bet > seat.dealer_stake {
+                return Err(BlackJackError::InsufficientBankroll);
+            }
+            seat.bet += bet;
+            let hand = &mut seat.hands[active as usize];
+            hand.split = true;
+            let mut split_hand = PlayerHand::new(bet, true);
+            if let Some(card) = hand.cards.pop() {
+                split_hand.cards.push(card);
+            }
+            hand.total = hand_value(&hand.cards).0;
+            split_hand.total = hand_value(&split_hand.cards).0;
+            seat.hands.insert(active as usize + 1, split_hand);
+            let index = index as u8;
+            self.request_cards(
+                PLAYER_SPLIT,
+                &[Hand::Player(index, active), Hand::Player(index, active + 1)],
+            );
+            Ok(bet)
+        }
+
+        /// Take the insurance of AMOUNT, or EVEN_MONEY, for the active seat, and pass the turn
+        /// to the next seat. Returns the insurance, which the player moves into the escrow.
+        fn insure(&mut self, amount: u64, even_money: bool) -> Result<u64, BlackJackError> {
+            let index = self.active_seat as usize;
+            let (natural, bet) = match self.active() {
+                Some(hand) => (hand.is_natural(), hand.bet),
+                None => return Err(BlackJackError::InvalidPhase),
+            };
+            let even_money_refused = even_money && (!natural || amount > 0);
+            let ace = self.dealer_up_card().is_some_and(|c| c.rank == Rank::Ace);
+            let declined = amount == 0 && !even_money;
+            if amount > bet / 2 || even_money_refused || (!ace && !declined) {
+                return Err(BlackJackError::InvalidInsurance);
+            }
+            let seat = &mut self.seats[index];
+            seat.insurance = amount;
+            seat.even_money = even_money;
+            self.last_operation = INSURANCE;
+            self.next_turn(index + 1);
+            Ok(amount)
+        }
+
+        /// Give up the hand of the active seat, as its first decision, for half the stake.
+        /// The turn passes to the next seat.
+        fn surrender(&mut self) -> Result<(), BlackJackError> {
+            let index = self.active_seat as usize;
+            let hands = &self.seats[index].hands;
+            let first_decision = hands.len() == 1 && hands[0].cards.len() == 2 && !hands[0].split;
+            let allowed = match self.rules.surrender {
+                SurrenderRule::Disabled => false,
+                SurrenderRule::Late => self.phase == Phase::PlayerTurn,
+                SurrenderRule::Early => true,
+            };
+            if !first_decision || !allowed {
+                return Err(BlackJackError::SurrenderNotAllowed);
+            }
+            self.last_operation = PLAYER_SURRENDER;
+            self.seats[index].hands[0].outcome = Some(Outcome::Surrender);
+            self.next_turn(index + 1);
+            Ok(())
+        }
+
+        /// Dealer tells if the hole card makes a NATURAL. A natural is revealed, otherwise
+        /// the hole card stays face down, and a natural revealed later forfeits the hand.
+        fn dealer_peek(&mut self, natural: bool) {
+            if natural {
+                self.request_cards(DEALER_PEEK, &[Hand::Hole]);
+            } else {
+                self.natural = Natural::Denied;
+                self.last_operation = DEALER_PEEK;
+                self.after_peek();
+            }
+        }
+    }
+    /// Check that OPERATION is allowed in the current phase of the game.
+    /// Game actions must also wait until all requested cards are revealed.
+    /// A new deck is sent between rounds, once the settled round is paid out, since the new
+    /// shoe clears its hands. Round interrupted by the end of the shoe keeps its hands, and
+    /// waits for the new shoe, and nothing else can be done meanwhile. Once the order of
+    /// the shoe is derived the dealer knows the coming cards, so the shoe is replaced only
+    /// when the table needs a new one, and can't be picked until the dealer likes the deal.
+    pub fn check_phase(bj_account: &BlackJackAccountData, operation: u8) -> ProgramResult {
+        // ended committed shoe is opened, before its round is paid out or it is replaced
+        let opening = match operation {
+            SEND_DECK | REQUEST_ORACLE_DECK => true,
+            PAYOUT => bj_account.round_unpaid(),
+            _ => false,
+        };
+        if opening && bj_account.shoe_unopened() {
+            msg!("Dealer must open the ended shoe first");
+            return Err(BlackJackError::ShoeNotOpened.into());
+        }
+        if bj_account.forfeited && (operation == DEAL || operation == OPEN_SHOE) {
+            return Err(BlackJackError::ShoeForfeited.into());
+        }
+        let phase = bj_account.phase;
+        let revealing = !bj_account.pending_draws.is_empty();
+        let between_rounds = bj_account.interrupted.is_none()
+            && (phase == Phase::WaitingForDeck || phase == Phase::Settled);
+        let allowed = match operation {
+            SEND_DECK | REQUEST_ORACLE_DECK => {
+                bj_account.needs_shoe()
+                    && (bj_account.interrupted.is_some()
+                        || (between_rounds && !bj_account.round_unpaid()))
+            }
+            PLAYER_COMMIT_SEED | PLAYER_REVEAL_SEED | DEALER_REVEAL_SEED | DROP_SEEDS => {
+                phase == Phase::WaitingForDeck
+            }
+            DEAL => between_rounds,
+            REVEAL_CARD | DRAW_ORACLE_CARD => {
+                phase != Phase::WaitingForDeck && phase != Phase::Settled
+            }
+            PLAYER_HIT | PLAYER_STAND | PLAYER_DOUBLE | PLAYER_SPLIT => {
+                phase == Phase::PlayerTurn && !revealing
+            }
+            INSURANCE => phase == Phase::Insurance && !revealing,
+            // early surrender is taken before the dealer peeks
+            PLAYER_SURRENDER => {
+                (phase == Phase::PlayerTurn || phase == Phase::Insurance) && !revealing
+            }
+            DEALER_PEEK => phase == Phase::Peek && !revealing,
+            DEALER_PLAY => phase == Phase::DealerTurn && !revealing,
+            OPEN_SHOE => bj_account.shoe_unopened(),
+            CLAIM_FORFEIT => bj_account.waits_for_dealer(),
+            SET_RULES | SET_MINT | PLACE_BET => between_rounds,
+            _ => true,
+        };
+        if !allowed {
+            msg!("Operation {} is not allowed in phase {:?}", operation, phase);
+            return Err(BlackJackError::InvalidPhase.into());
+        }
+        Ok(())
+    }
+    /// Give the dealer `DEALER_SLOTS` for its next step, once the operation made the round
+    /// wait for it. Deadline is restarted by every step of the dealer, and kept while the
+    /// round waits for it through the operations of the players. BEFORE is the table ahead
+    /// of the operation.
+    pub fn restart_dealer_deadline(
+        before: &BlackJackAccountData,
+        account_info: &AccountInfo,
+        is_dealer: bool,
+    ) -> ProgramResult {
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        if !bj_account.waits_for_dealer() || (!is_dealer && before.waits_for_dealer()) {
+            return Ok(());
+        }
+        bj_account.dealer_deadline = Clock::get()?.slot + DEALER_SLOTS;
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Check that PLAYER sits at the seat whose turn it is, for the player's game actions.
+    /// Seats take their turns in order, and the dealer plays after the last one.
+    pub fn check_turn(
+        bj_account: &BlackJackAccountData,
+        operation: u8,
+        player: &Pubkey,
+    ) -> ProgramResult {
+        match operation {
+            PLAYER_HIT | PLAYER_STAND | PLAYER_DOUBLE | PLAYER_SPLIT | INSURANCE
+            | PLAYER_SURRENDER => {}
+            _ => return Ok(()),
+        }
+        match bj_account.seat() {
+            Some(seat) if seat.player == *player => Ok(()),
+            _ => {
+                msg!("It is the turn of seat {}", bj_account.active_seat);
+                Err(BlackJackError::NotPlayersTurn.into())
+            }
+        }
+    }
+    /// Create the table account at the address derived from the dealer and the index
+    /// of the table, sized for the given number of decks. Dealer pays the rent, and the
+    /// program signs for the new account, which it owns from then on. The shoe of the new
+    /// table holds its decks, with the cut card at the default position.
+    pub fn unpack_create_table<'a>(
+        create_table: CreateTable,
+        program_id: &Pubkey,
+        account_info: &AccountInfo<'a>,
+        dealer_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        msg!("Create table {}", create_table.index);
+        if create_table.decks == 0 || create_table.decks > MAX_DECKS {
+            return Err(BlackJackError::InvalidRules.into());
+        }
+        let (address, bump) =
+            BlackJackAccountData::address(dealer_info.key, create_table.index, program_id);
+        if *account_info.key != address {
+            return Err(BlackJackError::InvalidTableAddress.into());
+        }
+        let size = match BlackJackAccountData::max_size(create_table.decks) {
+            Ok(size) => size,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        create_pda_account(
+            dealer_info,
+            account_info,
+            system_program_info,
+            size,
+            program_id,
+            &[
+                TABLE_SEED,
+                dealer_info.key.as_ref(),
+                &create_table.index.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        let mut bj_account = BlackJackAccountData::new(
+            *dealer_info.key,
+            create_table.index,
+            bump,
+            create_table.decks,
+        );
+        bj_account.last_operation = CREATE_TABLE;
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Table created");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Store the commitment to the received deck into the account. Proofs of the revealed
+    /// cards are verified against a tree over the table's shoe, and the dealer opens every
+    /// card of the shoe against the root once it ends, so a root over another number of
+    /// cards forfeits the round. Composition is checked as the cards are revealed, and once
+    /// the shoe is opened.
+    /// Players seated at the table keep their seats, and the table keeps its rules and stakes.
+    /// Round interrupted by the end of the previous shoe keeps its hands, and resumes once
+    /// the new shoe is shuffled.
+    pub fn unpack_send_deck(
+        send_deck_instruction: SendDeck,
+        account_info: &AccountInfo,
+    ) -> ProgramResult {
+        let table = match BlackJackAccountData::unpack(&account_info.data.borrow()) {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        let mut account = table;
+        account.new_shoe(DeckSource::Committed, SEND_DECK);
+        account.seed_deadline = Clock::get()?.slot + SEED_SLOTS;
+        account.deck_root = send_deck_instruction.deck_root;
+        account.dealer_seed_commitment = send_deck_instruction.seed_commitment;
+        msg!("Received deck commitment: {:?}", account.deck_root);
+
+        match account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Dealer opens the next cards of the ended committed shoe, in deck order, with the salts
+    /// of their positions. Cards are sent in batches which fit a transaction. Once the whole
+    /// shoe is opened, a shoe which does not match its commitment, or is stacked, forfeits
+    /// the round. The forfeit is kept, as the instruction succeeds, and the shoe is refused
+    /// from then on.
+    pub fn unpack_open_shoe(cards: Vec<OpenedCard>, account_info: &AccountInfo) -> ProgramResult {
+        msg!("Open shoe");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        bj_account.open_shoe(&cards)?;
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Opened {} of {} cards", bj_account.opened, bj_account.shoe_cards);
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Take a new shoe drawn by the oracle. This deck source replaces the dealer's deck
+    /// commitment: every card is drawn by the program when it is requested, with randomness
+    /// requested from the oracle program for that card. Randomness account of the table is
+    /// created by the program for the oracle at first, funded by the DEALER.
+    pub fn unpack_request_oracle_deck<'a>(
+        program_id: &Pubkey,
+        account_info: &AccountInfo<'a>,
+        dealer_info: &AccountInfo<'a>,
+        randomness_info: &AccountInfo<'a>,
+        oracle_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        msg!("Request oracle deck");
+        let bump = check_randomness(randomness_info, oracle_info, account_info.key, program_id)?;
+        if randomness_info.owner != oracle_info.key {
+            create_pda_account(
+                dealer_info,
+                randomness_info,
+                system_program_info,
+                mock_oracle::RANDOMNESS_SIZE,
+                oracle_info.key,
+                &[ORACLE_SEED, account_info.key.as_ref(), &[bump]],
+            )?;
+        }
+        let table = match BlackJackAccountData::unpack(&account_info.data.borrow()) {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        let mut account = table;
+        account.new_shoe(DeckSource::Oracle, REQUEST_ORACLE_DECK);
+        account.resume();
+
+        match account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Oracle deck received");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Draw the next requested card of the oracle's shoe, with a fresh random value requested
+    /// from the oracle for this card alone. Card is picked from the cards left in the shoe,
+    /// and given to the first hand waiting for a card. Hole card drawn at the peek is given
+    /// only if it makes a natural, otherwise it stays face down and is drawn again when
+    /// the dealer plays. Any seated player can draw, so the dealer can't stall the round.
+    pub fn unpack_draw_oracle_card<'a>(
+        program_id: &Pubkey,
+        account_info: &AccountInfo<'a>,
+        randomness_info: &AccountInfo<'a>,
+        oracle_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        msg!("Draw oracle card");
+        let bump = check_randomness(randomness_info, oracle_info, account_info.key, program_id)?;
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        if bj_account.deck_source != DeckSource::Oracle {
+            return Err(BlackJackError::CommittedDeck.into());
+        }
+        let hole = match bj_account.pending_draws.first() {
+            Some(hand) => *hand == Hand::Hole,
+            None => return Err(BlackJackError::NoPendingCard.into()),
+        };
+        let random = request_random(randomness_info, oracle_info, account_info.key, bump)?;
+        let card = match pick(random, &bj_account.oracle_weights()) {
+            Some(index) => card_at(index),
+            None => return Err(BlackJackError::InvalidDeck.into()),
+        };
+        let natural = bj_account.dealer_up_card().is_some_and(|up| is_natural(&[up, card]));
+        if hole && bj_account.pending_operation == DEALER_PEEK && !natural {
+            msg!("Dealer has no natural");
+            bj_account.pending_draws.remove(0);
+            bj_account.dealer_peek(false);
+        } else {
+            bj_account.dealt_cards[card.index()] += 1;
+            bj_account.give_next(card);
+        }
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Oracle card drawn");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Seat PLAYER at the next free seat of the table, up to MAX_SEATS. Player who is already
+    /// seated keeps its seat. Seat taken during a round is dealt in from the next round.
+    pub fn unpack_join_table(account_info: &AccountInfo, player: &Pubkey) -> ProgramResult {
+        msg!("Join table");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        if bj_account.seat_of(player).is_some() {
+            return Ok(());
+        }
+        if bj_account.seats.len() >= MAX_SEATS {
+            msg!("All {} seats are taken", MAX_SEATS);
+            return Err(BlackJackError::SeatTaken.into());
+        }
+        bj_account.seats.push(Seat::new(*player));
+        bj_account.last_operation = JOIN_TABLE;
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Player joined");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Store the player's commitment to its shuffle seed. Every seated player commits after
+    /// the dealer committed to the deck, and before the dealer's seed is revealed. Seat taken
+    /// after the dealer's seed is revealed is dealt in from the next shoe.
+    pub fn unpack_commit_seed(
+        commit_seed: CommitSeed,
+        account_info: &AccountInfo,
+        player: &Pubkey,
+    ) -> ProgramResult {
+        msg!("Commit seed");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        let index = match bj_account.seat_of(player) {
+            Some(i) => i,
+            None => return Err(BlackJackError::NotAuthorized.into()),
+        };
+        if bj_account.dealer_seed.is_some() {
+            msg!("Dealer's seed is revealed, seat is dealt in from the next shoe");
+            return Err(BlackJackError::InvalidPhase.into());
+        }
+        let seat = &mut bj_account.seats[index];
+        if seat.seed_commitment.is_some() {
+            return Err(BlackJackError::SeedAlreadyCommitted.into());
+        }
+        seat.seed_commitment = Some(commit_seed.seed_commitment);
+        bj_account.last_operation = PLAYER_COMMIT_SEED;
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Seed committed");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Reveal the player's or the dealer's shuffle seed. The seed must match its commitment.
+    /// Dealer reveals once every seat with a bet has committed to its seed, or once the seats
+    /// had `SEED_SLOTS` since the shoe was sent, and the seats which did not commit are not
+    /// dealt from the shoe. Seats then have `SEED_SLOTS` more to reveal their seeds. Once the dealer's
+    /// seed and the seeds of all committed seats are revealed, the order of dealing is
+    /// derived from them.
+    pub fn unpack_reveal_seed(
+        reveal_seed: RevealSeed,
+        account_info: &AccountInfo,
+        operation: u8,
+        signer: &Pubkey,
+    ) -> ProgramResult {
+        msg!("Reveal seed");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        let commitment = seed_hash(&reveal_seed.seed);
+        if operation == DEALER_REVEAL_SEED {
+            let slot = Clock::get()?.slot;
+            if !bj_account.seeds_committed() && slot < bj_account.seed_deadline {
+                msg!("Seats commit to their seeds until slot {}", bj_account.seed_deadline);
+                return Err(BlackJackError::SeedNotCommitted.into());
+            }
+            if commitment != bj_account.dealer_seed_commitment {
+                return Err(BlackJackError::SeedMismatch.into());
+            }
+            bj_account.dealer_seed = Some(reveal_seed.seed);
+            bj_account.seed_deadline = slot + SEED_SLOTS;
+        } else {
+            let seat = match bj_account.seat_of(signer) {
+                Some(i) => &mut bj_account.seats[i],
+                None => return Err(BlackJackError::NotAuthorized.into()),
+            };
+            match seat.seed_commitment {
+                Some(c) if c == commitment => seat.seed = Some(reveal_seed.seed),
+                Some(_) => return Err(BlackJackError::SeedMismatch.into()),
+                None => return Err(BlackJackError::SeedNotCommitted.into()),
+            }
+        }
+        bj_account.last_operation = operation;
+        bj_account.derive_order();
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Seed revealed");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Once the deadline to reveal the seeds has passed, drop the seats which did not reveal
+    /// theirs from the shuffle, so a seat can't hold the shoe back. The order is derived from
+    /// the revealed seeds, and the dropped seats are not dealt from the shoe.
+    pub fn unpack_drop_seeds(account_info: &AccountInfo) -> ProgramResult {
+        msg!("Drop seeds");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        if bj_account.dealer_seed.is_none() || bj_account.shuffled() {
+            return Err(BlackJackError::InvalidPhase.into());
+        }
+        if Clock::get()?.slot < bj_account.seed_deadline {
+            msg!("Seats reveal their seeds until slot {}", bj_account.seed_deadline);
+            return Err(BlackJackError::DeadlineNotPassed.into());
+        }
+        bj_account.drop_seeds();
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Unrevealed seeds dropped");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Player claims the dealt round once the dealer missed its deadline for its next step.
+    /// Round is forfeited, and the payout gives the players the house's cover. Only the
+    /// round in escrow is claimed, so a dealer which stacked the shoe and stops before its
+    /// end loses the cover of this round alone.
+    pub fn unpack_claim_forfeit(account_info: &AccountInfo) -> ProgramResult {
+        msg!("Claim forfeit");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        if Clock::get()?.slot < bj_account.dealer_deadline {
+            msg!("Dealer takes its next step until slot {}", bj_account.dealer_deadline);
+            return Err(BlackJackError::DeadlineNotPassed.into());
+        }
+        bj_account.forfeit_round();
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Round is forfeited to the players");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Deal the cards to the seated players and the dealer. Game starts with this operation.
+    /// Dealer's up card and hole card are dealt first, and then two cards for every seat dealt
+    /// from the shoe, in their order. Hole card is dealt face down, its position is reserved
+    /// and not revealed. Dealing is finished when the dealer reveals the other cards.
+    /// House vault covers the worst case of every dealt seat's bet into the escrow, once
+    /// the previous round is paid out. Tables with a mint take the cover from the house's
+    /// token account, followed by the vault and the token program.
+    pub fn unpack_deal<'a>(
+        program_id: &Pubkey,
+        account_info: &AccountInfo<'a>,
+        house_info: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        msg!("Received deal command");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        if !bj_account.shuffled() {
+            return Err(BlackJackError::DeckNotShuffled.into());
+        }
+        // bets placed for this round wait in escrow, the previous round must be paid out
+        if bj_account.round_unpaid() {
+            return Err(BlackJackError::EscrowNotEmpty.into());
+        }
+        // round is not dealt past the cut card, the dealer must send a new shoe first
+        if bj_account.current_card >= bj_account.cut_card as usize {
+            msg!("Cut card reached, shoe must be reshuffled");
+            bj_account.last_operation = REQUEST_NEW_DECK;
+            return match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+                Ok(_) => Ok(()),
+                Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+            };
+        }
+        let dealt: Vec<bool> = bj_account.seats.iter().map(|s| bj_account.dealt_in(s)).collect();
+        let mut stakes = Vec::with_capacity(dealt.len());
+        for (seat, &dealt) in bj_account.seats.iter().zip(&dealt) {
+            match worst_case_payout(seat.bet) {
+                Some(stake) if dealt => stakes.push(stake),
+                Some(_) => stakes.push(0),
+                None => return Err(BlackJackError::InvalidBet.into()),
+            }
+        }
+        let cover = match stakes.iter().try_fold(0u64, |sum, s| sum.checked_add(*s)) {
+            Some(cover) => cover,
+            None => return Err(BlackJackError::InsufficientBankroll.into()),
+        };
+        if cover > 0 {
+            let bump = check_house(house_info, account_info.key, program_id)?;
+            match bj_account.mint {
+                Some(mint) => {
+                    let accounts_iter = &mut token_accounts.iter();
+                    let house_token = next_account_info(accounts_iter)?;
+                    let vault = next_account_info(accounts_iter)?;
+                    let token_program = next_account_info(accounts_iter)?;
+                    check_token_account(house_token, house_info.key, &mint)?;
+                    check_vault(vault, account_info.key, &mint)?;
+                    if token_balance(house_token)? < cover {
+                        return Err(BlackJackError::InsufficientBankroll.into());
+                    }
+                    transfer_signed(
+                        house_token,
+                        vault,
+                        house_info,
+                        token_program,
+                        cover,
+                        &[HOUSE_SEED, account_info.key.as_ref(), &[bump]],
+                    )?;
+                }
+                None => {
+                    if lamport_bankroll(house_info, program_id)? < cover {
+                        return Err(BlackJackError::InsufficientBankroll.into());
+                    }
+                    **house_info.try_borrow_mut_lamports()? -= cover;
+                    **account_info.try_borrow_mut_lamports()? += cover;
+                }
+            }
+        }
+        bj_account.deal(&stakes);
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Deal requested");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Reveal the next card of the committed deck. The card is verified against
+    /// the deck commitment, and given to the first hand waiting for a card.
+    /// Deck positions are consumed in the order derived from the shuffle seeds, and
+    /// the hole card is revealed from its reserved position.
+    /// Card revealed more times than the shoe has decks proves the deck stacked, and forfeits
+    /// the round, so the players get the stakes in escrow with the house's cover.
+    pub fn unpack_reveal_card(
+        revealed: RevealedCard,
+        account_info: &AccountInfo,
+    ) -> ProgramResult {
+        msg!("Reveal card");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        if bj_account.deck_source != DeckSource::Committed {
+            return Err(BlackJackError::OracleDeck.into());
+        }
+        if bj_account.pending_draws.is_empty() {
+            return Err(BlackJackError::NoPendingCard.into());
+        }
+        let position = match bj_account.next_position() {
+            Some(p) => p,
+            None => {
+                return Err(BlackJackError::DeckNotShuffled.into());
+            }
+        };
+        let leaf = leaf_hash(position, &revealed.card, &revealed.salt);
+        if !verify_proof(
+            &bj_account.deck_root,
+            leaf,
+            position,
+            bj_account.shoe_cards as usize,
+            &revealed.proof,
+        ) {
+            return Err(BlackJackError::CardMismatch.into());
+        }
+        let decks = bj_account.shoe_cards / CARD_NUMBER as u16;
+        match bj_account.dealt_cards.get_mut(revealed.card.index()) {
+            Some(count) if (*count as u16) < decks => {
+                *count += 1;
+                bj_account.give_next(revealed.card);
+            }
+            _ => {
+                msg!("Card {:?} is revealed too many times, round is forfeited", revealed.card);
+                bj_account.forfeit_round();
+            }
+        }
 
-    #[derive(BorshSerialize, BorshDeserialize, Debug)]
-    pub struct BlackJackAccountData {
-        pub last_operation: u8, // last operation done on account
-        //initial dealer0 cards, at the game's beginning.
-        pub dealer_hand: u8, //At the beginning, contains init card  not visible to players. Later, contains sum of the dealer's cards.
-        pub dealer_start2: u8, // this card is visible to players.
-        pub player_hand: u8, // contatins sum of the player's cards.
-        pub current_card: usize, //current index inside the deck
-        pub cards: Vec<u8>,  // deck of cards
-    }
-    //instruction codes. Used in program, for switching.
-    pub const SEND_DECK: u8 = 0;
-    pub const DEAL: u8 = 1;
-    pub const REQUEST_NEW_DECK: u8 = 2;
-    pub const CLEAR_DATA: u8 = 3;
-    pub const PLAYER_HIT: u8 = 4;
-    pub const PLAYER_STAND: u8 = 5;
-    pub const DEALER_HIT: u8 = 6;
-    pub const DEALER_STAND: u8 = 7;
-    pub const PLAYER_BUSTED: u8 = 8;
-    pub const DEALER_BUSTED: u8 = 9;
-
-    //public constants
-    pub const CARD_NUMBER: u8 = 52;
-    /// Store  he received deck into the account.
-    pub fn unpack_send_deck(instruction_data: &[u8], account_info: &AccountInfo) {
-        let send_deck_instruction = match SendDeck::try_from_slice(instruction_data) {
-            Ok(sd) => sd,
-            Err(_) => {
-                msg!("Deserialization error");
-                return;
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Reveal finished");
+                Ok(())
             }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Seated player leaves its seat between rounds, once its seed of the shoe is revealed or
+    /// dropped. Dealer clears account data - set all to 0, and free every seat. Stakes in
+    /// escrow must be paid out first. Clearing drops the shoe, so the dealer clears only when
+    /// the table needs a new shoe anyway.
+    pub fn unpack_clear_data(account_info: &AccountInfo, signer: &Pubkey) -> ProgramResult {
+        msg!("Clear account");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
-        let mut account = BlackJackAccountData {
-            last_operation: SEND_DECK,
-            cards: send_deck_instruction.deck,
-            dealer_hand: 0,
-            dealer_start2: 0,
-            player_hand: 0,
-            current_card: 0,
+        if let Some(index) = bj_account.seat_of(signer) {
+            let seat = &bj_account.seats[index];
+            if seat.bet > 0 || seat.dealer_stake > 0 {
+                return Err(BlackJackError::EscrowNotEmpty.into());
+            }
+            let between_rounds = bj_account.interrupted.is_none()
+                && (bj_account.phase == Phase::WaitingForDeck
+                    || bj_account.phase == Phase::Settled);
+            if !between_rounds {
+                return Err(BlackJackError::InvalidPhase.into());
+            }
+            // seeds which shuffle the shoe are fixed once the dealer's seed is revealed
+            if seat.seed_commitment.is_some()
+                && bj_account.dealer_seed.is_some()
+                && !bj_account.shuffled()
+            {
+                msg!("Seat reveals its seed before it leaves");
+                return Err(BlackJackError::InvalidPhase.into());
+            }
+            bj_account.seats.remove(index);
+            bj_account.last_operation = CLEAR_DATA;
+            return match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+                Ok(_) => {
+                    msg!("Player left the table");
+                    Ok(())
+                }
+                Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+            };
+        }
+        if !bj_account.escrow_empty() {
+            return Err(BlackJackError::EscrowNotEmpty.into());
+        }
+        if !bj_account.needs_shoe() {
+            msg!("Shoe is dealt until its cut card");
+            return Err(BlackJackError::InvalidPhase.into());
+        }
+        bj_account.last_operation = CLEAR_DATA;
+        bj_account.phase = Phase::WaitingForDeck;
+        bj_account.seats.clear();
+        bj_account.active_seat = 0;
+        bj_account.dealer_hand = 0;
+        bj_account.dealer_cards.clear();
+        bj_account.hole_card = None;
+        bj_account.natural = Natural::Possible;
+        bj_account.deck_source = DeckSource::Committed;
+        bj_account.deck_root = [0; 32];
+        bj_account.dealer_seed = None;
+        bj_account.deck_order.clear();
+        bj_account.current_card = 0;
+        bj_account.dealt_cards.clear();
+        bj_account.opened = 0;
+        bj_account.opening.clear();
+        bj_account.forfeited = false;
+        bj_account.pending_draws.clear();
+        bj_account.interrupted = None;
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Clearing finished");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+
+    /// Request one more card for the active hand of the seat whose turn it is. Once the card
+    /// is given, a busted hand passes the turn to the next hand.
+    pub fn unpack_hit(account_info: &AccountInfo) -> ProgramResult {
+        msg!("Hit");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
-        account.current_card = account.cards.len() - 1;
-        msg!("Received deck: {:?}", account.cards);
 
-        match account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+        let seat = bj_account.active_seat;
+        let active = bj_account.seats[seat as usize].active_hand;
+        bj_account.request_cards(PLAYER_HIT, &[Hand::Player(seat, active)]);
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
             Ok(_) => {
-                return;
+                msg!("Hit requested");
+                Ok(())
             }
-            Err(_) => {
-                msg!("Account serialization error");
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Player's stand passes the turn to its next hand, after the last one to the next seat,
+    /// and after the last seat to the dealer.
+    pub fn unpack_stand(account_info: &AccountInfo) -> ProgramResult {
+        msg!("Stand");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+
+        bj_account.last_operation = PLAYER_STAND;
+        bj_account.finish_hand();
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Stand operation finished");
+                Ok(())
             }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Play out the dealer's hand by the table rules, and settle the round. Dealer's hand is
+    /// played by the rules alone, so any seated player can start it if the dealer does not.
+    pub fn unpack_dealer_play(account_info: &AccountInfo) -> ProgramResult {
+        msg!("Dealer play");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
+
+        bj_account.play_dealer();
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Dealer play requested");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
     }
-    /// Deal the cards to the player and dealer. Game starts with this operation.
-    /// Cards are consumed from the deck's highest index.
+    /// Dealer of the committed deck tells if its hole card makes a natural, when it shows
+    /// an ace or a ten. A natural is revealed from the hole card's position, and settles
+    /// the round. Otherwise the hole card stays face down until the dealer plays, and a natural
+    /// revealed then forfeits the dealer's hand.
+    pub fn unpack_dealer_peek(natural: bool, account_info: &AccountInfo) -> ProgramResult {
+        msg!("Dealer peek");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
 
-    pub fn unpack_deal(account_info: &AccountInfo) {
-        msg!("Received deal command");
-        let mut bj_account = match BlackJackAccountData::try_from_slice(&account_info.data.borrow())
+        bj_account.dealer_peek(natural);
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Dealer peek finished");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Store the house rules of the table. Rules are changed only between rounds.
+    /// The shoe holds from 1 to MAX_DECKS decks, as many as the table account was sized for
+    /// when it was created, and the cut card lies within the shoe.
+    pub fn unpack_set_rules(rules: TableRules, account_info: &AccountInfo) -> ProgramResult {
+        msg!("Set rules");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
         {
             Ok(acc) => acc,
-            Err(_) => {
-                msg!("Account serialization error");
-                return;
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+
+        if rules.decks == 0
+            || rules.decks > MAX_DECKS
+            || rules.cut_card == 0
+            || rules.cut_card > rules.shoe_cards()
+        {
+            return Err(BlackJackError::InvalidRules.into());
+        }
+        match BlackJackAccountData::max_size(rules.decks) {
+            Ok(size) if size <= account_info.data_len() => {}
+            Ok(size) => {
+                msg!("Shoe of {} decks needs {} bytes of the table account", rules.decks, size);
+                return Err(BlackJackError::InvalidRules.into());
             }
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        }
+
+        bj_account.rules = rules;
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Rules set");
+                Ok(())
+            }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Move the player's stake into its seat's escrow held by the table account.
+    /// Bet is placed between rounds, once per round. Bet is refused if the house vault
+    /// can't cover its worst case, on top of the other seats' bets waiting for the deal.
+    /// Tables with a mint take the stake from the player's token account, followed by
+    /// the vault, the token program and the house's token account.
+    pub fn unpack_place_bet<'a>(
+        place_bet: PlaceBet,
+        program_id: &Pubkey,
+        account_info: &AccountInfo<'a>,
+        player_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        house_info: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        msg!("Place bet");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        if place_bet.amount == 0 {
+            return Err(BlackJackError::InvalidBet.into());
+        }
+        let index = match bj_account.seat_of(player_info.key) {
+            Some(i) => i,
+            None => return Err(BlackJackError::NotAuthorized.into()),
+        };
+        if bj_account.seats[index].bet > 0 || bj_account.seats[index].dealer_stake > 0 {
+            return Err(BlackJackError::EscrowNotEmpty.into());
+        }
+        check_house(house_info, account_info.key, program_id)?;
+        // bet whose worst case overflows can't be covered by any bankroll
+        let worst_case = match worst_case_payout(place_bet.amount) {
+            Some(payout) => payout,
+            None => return Err(BlackJackError::InvalidBet.into()),
+        };
+        let worst_case = match bj_account.reserved_cover().and_then(|r| r.checked_add(worst_case)) {
+            Some(worst_case) => worst_case,
+            None => return Err(BlackJackError::InsufficientBankroll.into()),
         };
-        let mut no_card_left = false;
-        match bj_account.cards.get_mut(bj_account.current_card) {
-            Some(c) => {
-                bj_account.dealer_hand = *c;
-                *c = 0;
-                bj_account.current_card -= 1;
+        match bj_account.mint {
+            Some(mint) => {
+                let accounts_iter = &mut token_accounts.iter();
+                let player_token = next_account_info(accounts_iter)?;
+                let vault = next_account_info(accounts_iter)?;
+                let token_program = next_account_info(accounts_iter)?;
+                let house_token = next_account_info(accounts_iter)?;
+                check_token_account(player_token, player_info.key, &mint)?;
+                check_vault(vault, account_info.key, &mint)?;
+                check_token_account(house_token, house_info.key, &mint)?;
+                if token_balance(house_token)? < worst_case {
+                    return Err(BlackJackError::InsufficientBankroll.into());
+                }
+                deposit(player_token, vault, player_info, token_program, place_bet.amount)?;
             }
             None => {
-                msg!("No cards left to deal!");
-                bj_account.last_operation = REQUEST_NEW_DECK;
-                no_card_left = true;
-            }
-        };
-        match bj_account.cards.get_mut(bj_account.current_card) {
-            Some(c) => {
-                if !no_card_left {
-                    bj_account.dealer_start2 = *c;
-                    bj_account.dealer_hand += *c;
-                    *c = 0;
-                    bj_account.current_card -= 1;
+                if lamport_bankroll(house_info, program_id)? < worst_case {
+                    return Err(BlackJackError::InsufficientBankroll.into());
                 }
+                invoke(
+                    &system_instruction::transfer(
+                        player_info.key,
+                        account_info.key,
+                        place_bet.amount,
+                    ),
+                    &[
+                        player_info.clone(),
+                        account_info.clone(),
+                        system_program_info.clone(),
+                    ],
+                )?
             }
-            None => {
-                msg!("No cards left to deal!");
-                bj_account.last_operation = REQUEST_NEW_DECK;
-                no_card_left = true;
+        }
+        bj_account.seats[index].bet = place_bet.amount;
+        bj_account.last_operation = PLACE_BET;
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Bet placed");
+                Ok(())
             }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Player takes back its bet which is not dealt yet, from the escrow of the table.
+    /// A bet is not in play until the house covers it, so it is withdrawn in any phase,
+    /// for example when the seat is not dealt from the shoe. Tables with a mint pay
+    /// from the vault, followed by the player's token account and the token program.
+    pub fn unpack_withdraw_bet<'a>(
+        account_info: &AccountInfo<'a>,
+        player_info: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        msg!("Withdraw bet");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
-        match bj_account.cards.get_mut(bj_account.current_card) {
-            Some(c) => {
-                if !no_card_left {
-                    bj_account.player_hand = *c;
-                    *c = 0;
-                    bj_account.current_card -= 1;
-                }
+        let index = match bj_account.seat_of(player_info.key) {
+            Some(i) => i,
+            None => return Err(BlackJackError::NotAuthorized.into()),
+        };
+        let seat = &bj_account.seats[index];
+        if seat.bet == 0 {
+            return Err(BlackJackError::InvalidBet.into());
+        }
+        if seat.dealer_stake > 0 {
+            msg!("Bet is dealt, and is paid out with the round");
+            return Err(BlackJackError::InvalidPhase.into());
+        }
+        let bet = seat.bet;
+        match bj_account.mint {
+            Some(mint) => {
+                let accounts_iter = &mut token_accounts.iter();
+                let player_token = next_account_info(accounts_iter)?;
+                let vault = next_account_info(accounts_iter)?;
+                let token_program = next_account_info(accounts_iter)?;
+                check_token_account(player_token, player_info.key, &mint)?;
+                check_vault(vault, account_info.key, &mint)?;
+                let table_index = bj_account.index.to_le_bytes();
+                transfer_signed(
+                    vault,
+                    player_token,
+                    account_info,
+                    token_program,
+                    bet,
+                    &[
+                        TABLE_SEED,
+                        bj_account.dealer.as_ref(),
+                        &table_index,
+                        &[bj_account.bump],
+                    ],
+                )?;
             }
             None => {
-                msg!("No cards left to deal!");
-                bj_account.last_operation = REQUEST_NEW_DECK;
-                no_card_left = true;
+                **account_info.try_borrow_mut_lamports()? -= bet;
+                **player_info.try_borrow_mut_lamports()? += bet;
+            }
+        }
+        bj_account.seats[index].bet = 0;
+        bj_account.last_operation = WITHDRAW_BET;
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Bet of {} withdrawn", bet);
+                Ok(())
             }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Pay the escrow of every seat dealt in the round out to its player and the house vault,
+    /// by the outcome of the settled round: 1:1 on a win, 3:2 on a natural, stake returned
+    /// on a push, and the rest to the house. Bets placed for the next round stay in escrow,
+    /// and nothing happens when the round is already paid out. Hands of the paid seats are
+    /// cleared. The house vault is followed by the wallets of the paid seats, in their order.
+    /// Tables with a mint pay from the vault into the house's token account, followed by
+    /// the vault, the token program and the players' token accounts. Table signs
+    /// for the transfers out of its vault.
+    pub fn unpack_payout<'a>(
+        program_id: &Pubkey,
+        account_info: &AccountInfo<'a>,
+        house_info: &AccountInfo<'a>,
+        accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        msg!("Payout");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
-        match bj_account.cards.get_mut(bj_account.current_card) {
-            Some(c) => {
-                if !no_card_left {
-                    bj_account.player_hand += *c;
-                    *c = 0;
-                    bj_account.current_card -= 1;
+        if !bj_account.round_unpaid() {
+            return Ok(());
+        }
+        if bj_account.phase != Phase::Settled {
+            return Err(BlackJackError::InvalidPhase.into());
+        }
+        check_house(house_info, account_info.key, program_id)?;
+        let dealer_natural = bj_account.dealer_natural();
+        let accounts_iter = &mut accounts.iter();
+        let mut house_payout = 0;
+        match bj_account.mint {
+            Some(mint) => {
+                let house_token = next_account_info(accounts_iter)?;
+                let vault = next_account_info(accounts_iter)?;
+                let token_program = next_account_info(accounts_iter)?;
+                check_token_account(house_token, house_info.key, &mint)?;
+                check_vault(vault, account_info.key, &mint)?;
+                let index = bj_account.index.to_le_bytes();
+                let seeds: &[&[u8]] = &[
+                    TABLE_SEED,
+                    bj_account.dealer.as_ref(),
+                    &index,
+                    &[bj_account.bump],
+                ];
+                for seat in bj_account.seats.iter().filter(|s| s.unpaid()) {
+                    let player_token = next_account_info(accounts_iter)?;
+                    check_token_account(player_token, &seat.player, &mint)?;
+                    let (player_payout, house) = seat.payouts(dealer_natural);
+                    transfer_signed(
+                        vault,
+                        player_token,
+                        account_info,
+                        token_program,
+                        player_payout,
+                        seeds,
+                    )?;
+                    msg!("Paid {} to the player {}", player_payout, seat.player);
+                    house_payout += house;
                 }
+                transfer_signed(
+                    vault,
+                    house_token,
+                    account_info,
+                    token_program,
+                    house_payout,
+                    seeds,
+                )?;
             }
             None => {
-                msg!("No cards left to deal!");
-                bj_account.last_operation = REQUEST_NEW_DECK;
-                no_card_left = true;
+                for seat in bj_account.seats.iter().filter(|s| s.unpaid()) {
+                    let player_info = next_account_info(accounts_iter)?;
+                    if *player_info.key != seat.player {
+                        return Err(BlackJackError::WrongAccount.into());
+                    }
+                    let (player_payout, house) = seat.payouts(dealer_natural);
+                    **account_info.try_borrow_mut_lamports()? -= player_payout + house;
+                    **player_info.try_borrow_mut_lamports()? += player_payout;
+                    msg!("Paid {} to the player {}", player_payout, seat.player);
+                    house_payout += house;
+                }
+                **house_info.try_borrow_mut_lamports()? += house_payout;
             }
-        };
-        if !no_card_left {
-            bj_account.last_operation = DEAL;
         }
+        msg!("Paid {} to the house", house_payout);
+        for seat in bj_account.seats.iter_mut().filter(|s| s.unpaid()) {
+            seat.bet = 0;
+            seat.dealer_stake = 0;
+            seat.insurance = 0;
+            seat.even_money = false;
+            seat.hands.clear();
+            seat.active_hand = 0;
+        }
+        bj_account.last_operation = PAYOUT;
 
         match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
             Ok(_) => {
-                msg!("Deal finished, account: {:?}", bj_account);
-                return;
-            }
-            Err(_) => {
-                msg!("Account serialization error");
+                msg!("Payout finished");
+                Ok(())
             }
-        };
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
     }
-    /// Clear account data - set all to 0
-    pub fn unpack_clear_data(account_info: &AccountInfo) {
-        msg!("Clear account");
-        let mut bj_account = match BlackJackAccountData::try_from_slice(&account_info.data.borrow())
+    /// Set the mint of the tokens staked at the table, or stake lamports if no mint is given.
+    /// Mint is changed only between rounds, with an empty escrow.
+    pub fn unpack_set_mint(mint: Option<Pubkey>, account_info: &AccountInfo) -> ProgramResult {
+        msg!("Set mint");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
         {
             Ok(acc) => acc,
-            Err(_) => {
-                msg!("Account serialization error");
-                return;
-            }
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
-        bj_account.last_operation = CLEAR_DATA;
-        bj_account.dealer_hand = 0;
-        bj_account.dealer_hand = 2;
-        bj_account.current_card = 0;
-        bj_account.player_hand = 0;
-        let len = bj_account.cards.len();
-        bj_account.cards.clear();
-        for _ in 0..len {
-            bj_account.cards.push(0);
+        if !bj_account.escrow_empty() {
+            return Err(BlackJackError::EscrowNotEmpty.into());
         }
+
+        bj_account.mint = mint;
         match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
             Ok(_) => {
-                msg!("Clearing finished, account: {:?}", bj_account);
-                return;
+                msg!("Mint set");
+                Ok(())
             }
-            Err(_) => {
-                msg!("Account serialization error");
-            }
-        };
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
     }
-
-    pub fn unpack_hit(account_info: &AccountInfo, operation: u8) {
-        msg!("Hit");
-        let mut bj_account = match BlackJackAccountData::try_from_slice(&account_info.data.borrow())
-        {
+    /// Move the dealer's lamports, or tokens of the table's mint, into the house vault.
+    /// The vault account is created by the first deposit. Tables with a mint take
+    /// the deposit from the dealer's token account, followed by the house's token account
+    /// and the token program.
+    pub fn unpack_house_deposit<'a>(
+        transfer: HouseTransfer,
+        program_id: &Pubkey,
+        account_info: &AccountInfo<'a>,
+        dealer_info: &AccountInfo<'a>,
+        house_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        msg!("House deposit");
+        let bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow()) {
             Ok(acc) => acc,
-            Err(_) => {
-                msg!("Account serialization error");
-                return;
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        let bump = check_house(house_info, account_info.key, program_id)?;
+        match bj_account.mint {
+            Some(mint) => {
+                let accounts_iter = &mut token_accounts.iter();
+                let dealer_token = next_account_info(accounts_iter)?;
+                let house_token = next_account_info(accounts_iter)?;
+                let token_program = next_account_info(accounts_iter)?;
+                check_token_account(dealer_token, dealer_info.key, &mint)?;
+                check_token_account(house_token, house_info.key, &mint)?;
+                deposit(dealer_token, house_token, dealer_info, token_program, transfer.amount)?;
+            }
+            None => {
+                if house_info.owner != program_id {
+                    create_pda_account(
+                        dealer_info,
+                        house_info,
+                        system_program_info,
+                        0,
+                        program_id,
+                        &[HOUSE_SEED, account_info.key.as_ref(), &[bump]],
+                    )?;
+                }
+                invoke(
+                    &system_instruction::transfer(
+                        dealer_info.key,
+                        house_info.key,
+                        transfer.amount,
+                    ),
+                    &[
+                        dealer_info.clone(),
+                        house_info.clone(),
+                        system_program_info.clone(),
+                    ],
+                )?;
             }
+        }
+        msg!("Deposited {} into the house vault", transfer.amount);
+        Ok(())
+    }
+    /// Move lamports, or tokens of the table's mint, from the house vault back to the dealer.
+    /// Vault must still cover the worst case of the bets which are placed, but not dealt yet.
+    /// Tables with a mint pay into the dealer's token account, preceded by the house's
+    /// token account and followed by the token program.
+    pub fn unpack_house_withdraw<'a>(
+        transfer: HouseTransfer,
+        program_id: &Pubkey,
+        account_info: &AccountInfo<'a>,
+        dealer_info: &AccountInfo<'a>,
+        house_info: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        msg!("House withdraw");
+        let bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow()) {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
-
-        match bj_account.cards.get_mut(bj_account.current_card) {
-            Some(c) => {
-                if operation == PLAYER_HIT {
-                    bj_account.player_hand += *c;
-                } else if operation == DEALER_HIT {
-                    bj_account.dealer_hand += *c;
+        let bump = check_house(house_info, account_info.key, program_id)?;
+        let required = match bj_account
+            .reserved_cover()
+            .and_then(|r| r.checked_add(transfer.amount))
+        {
+            Some(required) => required,
+            None => return Err(BlackJackError::InsufficientBankroll.into()),
+        };
+        match bj_account.mint {
+            Some(mint) => {
+                let accounts_iter = &mut token_accounts.iter();
+                let house_token = next_account_info(accounts_iter)?;
+                let dealer_token = next_account_info(accounts_iter)?;
+                let token_program = next_account_info(accounts_iter)?;
+                check_token_account(house_token, house_info.key, &mint)?;
+                check_token_account(dealer_token, dealer_info.key, &mint)?;
+                if token_balance(house_token)? < required {
+                    return Err(BlackJackError::InsufficientBankroll.into());
                 }
-                *c = 0;
-                bj_account.current_card -= 1;
+                transfer_signed(
+                    house_token,
+                    dealer_token,
+                    house_info,
+                    token_program,
+                    transfer.amount,
+                    &[HOUSE_SEED, account_info.key.as_ref(), &[bump]],
+                )?;
             }
             None => {
-                msg!("No cards left to deal!");
-                bj_account.last_operation = REQUEST_NEW_DECK;
-                return;
+                if lamport_bankroll(house_info, program_id)? < required {
+                    return Err(BlackJackError::InsufficientBankroll.into());
+                }
+                **house_info.try_borrow_mut_lamports()? -= transfer.amount;
+                **dealer_info.try_borrow_mut_lamports()? += transfer.amount;
             }
+        }
+        msg!("Withdrew {} from the house vault", transfer.amount);
+        Ok(())
+    }
+    /// Double the bet of the active hand of the seat whose turn it is, and request exactly
+    /// one more card for it. Once the card is given, the turn passes to the next hand, or seat.
+    /// Player doubles only on the first two cards of a hand, with a total allowed by the rules.
+    /// Tables with a mint take the stake from the player's token account, followed by
+    /// the vault and the token program.
+    pub fn unpack_double<'a>(
+        account_info: &AccountInfo<'a>,
+        player_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        msg!("Double");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
+        let stake = bj_account.double()?;
+
+        add_stake(
+            &bj_account,
+            account_info,
+            player_info,
+            system_program_info,
+            token_accounts,
+            stake,
+        )?;
 
-        bj_account.last_operation = operation;
         match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
             Ok(_) => {
-                msg!("Hit operation finished, account: {:?}", bj_account);
-                return;
+                msg!("Double requested");
+                Ok(())
             }
-            Err(_) => {
-                msg!("Account serialization error");
-            }
-        };
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
     }
-    pub fn unpack_stand(account_info: &AccountInfo, operation: u8) {
-        msg!("Stand");
-        let mut bj_account = match BlackJackAccountData::try_from_slice(&account_info.data.borrow())
+    /// Split the pair of the active hand of the seat whose turn it is into two hands, each with
+    /// the bet of the pair, and request one more card for each of them. Hands are split again only
+    /// if the table rules allow it. Split aces receive one card each, if the rules say so.
+    /// Tables with a mint take the stake from the player's token account, followed by
+    /// the vault and the token program.
+    pub fn unpack_split<'a>(
+        account_info: &AccountInfo<'a>,
+        player_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        msg!("Split");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
         {
             Ok(acc) => acc,
-            Err(_) => {
-                msg!("Account serialization error");
-                return;
-            }
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
+        let stake = bj_account.split()?;
+
+        add_stake(
+            &bj_account,
+            account_info,
+            player_info,
+            system_program_info,
+            token_accounts,
+            stake,
+        )?;
 
-        bj_account.last_operation = operation;
         match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
             Ok(_) => {
-                msg!("Stand operation finished, account: {:?}", bj_account);
-                return;
-            }
-            Err(_) => {
-                msg!("Account serialization error");
+                msg!("Split requested");
+                Ok(())
             }
-        };
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
     }
-
-    pub fn unpack_busted(account_info: &AccountInfo, operation: u8) {
-        msg!("Busted");
-        let mut bj_account = match BlackJackAccountData::try_from_slice(&account_info.data.borrow())
+    /// Take the insurance, or even money, of the seat whose turn it is when the dealer shows
+    /// an ace. Once every seat decided, the dealer peeks at the hole card: a natural is revealed
+    /// and settles the round, paying the insurance 2:1. Otherwise the hole card stays face down
+    /// and the seats play their hands, and the insurance is lost once it is revealed. Even money
+    /// decides the seat's natural right away. Tables with a mint take the insurance from
+    /// the player's token account, followed by the vault and the token program.
+    pub fn unpack_insurance<'a>(
+        insurance: Insurance,
+        account_info: &AccountInfo<'a>,
+        player_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        msg!("Insurance");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
         {
             Ok(acc) => acc,
-            Err(_) => {
-                msg!("Account serialization error");
-                return;
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+        };
+        let stake = bj_account.insure(insurance.amount, insurance.even_money)?;
+        add_stake(
+            &bj_account,
+            account_info,
+            player_info,
+            system_program_info,
+            token_accounts,
+            stake,
+        )?;
+
+        match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
+            Ok(_) => {
+                msg!("Insurance decided");
+                Ok(())
             }
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Player of the seat whose turn it is gives up its hand as the first decision, and gets
+    /// back half the stake. The turn passes to the next seat.
+    /// Late surrender is taken after the dealer's peek, so a dealer's natural has already
+    /// settled the round. Early surrender is taken even against the dealer's natural: seats
+    /// decide on it before the peek under an ace or a ten, instead of the insurance.
+    pub fn unpack_surrender(account_info: &AccountInfo) -> ProgramResult {
+        msg!("Surrender");
+        let mut bj_account = match BlackJackAccountData::unpack(&account_info.data.borrow())
+        {
+            Ok(acc) => acc,
+            Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
         };
+        bj_account.surrender()?;
 
-        bj_account.last_operation = operation;
         match bj_account.serialize(&mut &mut account_info.data.borrow_mut()[..]) {
             Ok(_) => {
-                msg!("Busted operation finished, account: {:?}", bj_account);
-                return;
+                msg!("Surrender finished");
+                Ok(())
             }
-            Err(_) => {
-                msg!("Account serialization error");
+            Err(_) => Err(BlackJackError::AccountSerializationError.into()),
+        }
+    }
+    /// Move AMOUNT more of the player's stake into the escrow, for a double, a split
+    /// or an insurance.
+    /// Tables with a mint take it from the player's token account, followed by the vault
+    /// and the token program.
+    fn add_stake<'a>(
+        bj_account: &BlackJackAccountData,
+        account_info: &AccountInfo<'a>,
+        player_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+        amount: u64,
+    ) -> ProgramResult {
+        if amount == 0 {
+            return Ok(());
+        }
+        match bj_account.mint {
+            Some(mint) => {
+                let accounts_iter = &mut token_accounts.iter();
+                let player_token = next_account_info(accounts_iter)?;
+                let vault = next_account_info(accounts_iter)?;
+                let token_program = next_account_info(accounts_iter)?;
+                check_token_account(player_token, player_info.key, &mint)?;
+                check_vault(vault, account_info.key, &mint)?;
+                deposit(player_token, vault, player_info, token_program, amount)
             }
-        };
+            None => invoke(
+                &system_instruction::transfer(player_info.key, account_info.key, amount),
+                &[
+                    player_info.clone(),
+                    account_info.clone(),
+                    system_program_info.clone(),
+                ],
+            ),
+        }
     }
 
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use black_jack_core::card::Suit;
+        use black_jack_core::state::DoubleTotals;
+
+        // Card of RANK. Suits don't change totals.
+        fn card(rank: Rank) -> Card {
+            Card {
+                rank,
+                suit: Suit::Spades,
+            }
+        }
+
+        // Table of one oracle's deck, with a seat betting 100 for every one of HANDS. Dealer is
+        // dealt the UP card, and every seat the two cards of its hand.
+        fn dealt_table(rules: TableRules, up: Rank, hands: &[[Rank; 2]]) -> BlackJackAccountData {
+            dealt_from(DeckSource::Oracle, rules, up, hands)
+        }
+
+        // Table dealt like `dealt_table`, from a shoe of SOURCE. Every seat shuffled the committed
+        // deck.
+        fn dealt_from(
+            source: DeckSource,
+            rules: TableRules,
+            up: Rank,
+            hands: &[[Rank; 2]],
+        ) -> BlackJackAccountData {
+            let mut table = new_table(rules);
+            table.new_shoe(source, REQUEST_ORACLE_DECK);
+            for _ in hands {
+                let mut seat = Seat::new(Pubkey::new_unique());
+                seat.bet = 100;
+                seat.seed = Some([0; 32]);
+                table.seats.push(seat);
+            }
+            table.deal(&vec![worst_case_payout(100).unwrap(); hands.len()]);
+            table.give_next(card(up));
+            for hand in hands {
+                table.give_next(card(hand[0]));
+                table.give_next(card(hand[1]));
+            }
+            table
+        }
+
+        // Table of the RULES, with no seat taken and no shoe sent.
+        fn new_table(rules: TableRules) -> BlackJackAccountData {
+            let mut table = BlackJackAccountData::new(Pubkey::new_unique(), 0, 0, rules.decks);
+            table.rules = rules;
+            table
+        }
+
+        fn rules() -> TableRules {
+            TableRules {
+                hit_soft_17: false,
+                double_on: DoubleTotals::Any,
+                resplit: true,
+                split_aces_one_card: true,
+                surrender: SurrenderRule::Late,
+                decks: 1,
+                cut_card: 39,
+            }
+        }
+
+        #[test]
+        fn unknown_instruction_tag_is_an_invalid_instruction() {
+            let unpack = BlackJackInstruction::unpack;
+            assert_eq!(unpack(&[]).unwrap_err(), BlackJackError::InvalidInstruction);
+            assert_eq!(unpack(&[255]).unwrap_err(), BlackJackError::InvalidInstruction);
+            // send deck without its commitments
+            assert_eq!(unpack(&[0]).unwrap_err(), BlackJackError::DeserializationError);
+            assert!(matches!(unpack(&[1]), Ok(BlackJackInstruction::Deal)));
+        }
+
+        #[test]
+        fn seats_take_their_turns_in_order_and_the_dealer_plays_last() {
+            let mut table = dealt_table(
+                rules(),
+                Rank::Seven,
+                &[
+                    [Rank::Two, Rank::Three],
+                    [Rank::Ten, Rank::Ace],
+                    [Rank::Five, Rank::Six],
+                ],
+            );
+            assert_eq!(table.phase, Phase::PlayerTurn);
+            assert_eq!(table.active_seat, 0);
+            table.finish_hand();
+            // natural of the second seat is settled without its turn
+            assert_eq!(table.active_seat, 2);
+            assert_eq!(table.phase, Phase::PlayerTurn);
+            table.finish_hand();
+            assert_eq!(table.phase, Phase::DealerTurn);
+        }
+
+        #[test]
+        fn double_takes_one_card_and_passes_the_turn() {
+            let mut table = dealt_table(rules(), Rank::Seven, &[[Rank::Five, Rank::Six]; 2]);
+            assert_eq!(table.phase, Phase::PlayerTurn);
+            assert_eq!(table.double(), Ok(100));
+            assert_eq!(table.seats[0].bet, 200);
+            assert_eq!(table.seats[0].hands[0].bet, 200);
+            assert_eq!(table.pending_draws, vec![Hand::Player(0, 0)]);
+            table.give_next(card(Rank::Two));
+            assert_eq!(table.seats[0].hands[0].total, 13);
+            assert_eq!(table.last_operation, PLAYER_DOUBLE);
+            assert_eq!(table.active_seat, 1);
+        }
+
+        #[test]
+        fn busted_double_passes_the_turn() {
+            let mut table = dealt_table(rules(), Rank::Seven, &[[Rank::Ten, Rank::Six]; 2]);
+            table.double().unwrap();
+            table.give_next(card(Rank::King));
+            assert_eq!(table.last_operation, PLAYER_BUSTED);
+            assert_eq!(table.active_seat, 1);
+        }
+
+        #[test]
+        fn double_is_refused_outside_the_totals_of_the_rules() {
+            let rules = TableRules {
+                double_on: DoubleTotals::TenOrEleven,
+                ..rules()
+            };
+            let mut table = dealt_table(rules, Rank::Seven, &[[Rank::Four, Rank::Five]]);
+            assert_eq!(table.double(), Err(BlackJackError::DoubleNotAllowed));
+            let mut table = dealt_table(rules, Rank::Seven, &[[Rank::Four, Rank::Six]]);
+            assert_eq!(table.double(), Ok(100));
+        }
+
+        #[test]
+        fn double_is_refused_after_a_hit() {
+            let mut table = dealt_table(rules(), Rank::Seven, &[[Rank::Two, Rank::Three]]);
+            table.request_cards(PLAYER_HIT, &[Hand::Player(0, 0)]);
+            table.give_next(card(Rank::Four));
+            assert_eq!(table.double(), Err(BlackJackError::DoubleNotAllowed));
+            assert_eq!(table.seats[0].bet, 100);
+        }
+
+        #[test]
+        fn split_deals_a_card_to_each_hand() {
+            let mut table = dealt_table(rules(), Rank::Seven, &[[Rank::Eight, Rank::Eight]]);
+            assert_eq!(table.split(), Ok(100));
+            assert_eq!(table.seats[0].bet, 200);
+            assert_eq!(table.pending_draws, vec![Hand::Player(0, 0), Hand::Player(0, 1)]);
+            table.give_next(card(Rank::Three));
+            table.give_next(card(Rank::Ten));
+            let hands = &table.seats[0].hands;
+            assert_eq!((hands[0].total, hands[1].total), (11, 18));
+            assert!(hands.iter().all(|h| h.split && h.bet == 100));
+            assert_eq!(table.seats[0].active_hand, 0);
+            assert_eq!(table.phase, Phase::PlayerTurn);
+        }
+
+        #[test]
+        fn split_hand_is_split_again_only_by_the_rules() {
+            for resplit in [true, false] {
+                let rules = TableRules { resplit, ..rules() };
+                let mut table = dealt_table(rules, Rank::Seven, &[[Rank::Eight, Rank::Eight]]);
+                table.split().unwrap();
+                table.give_next(card(Rank::Eight));
+                table.give_next(card(Rank::Two));
+                if resplit {
+                    assert_eq!(table.split(), Ok(100));
+                    assert_eq!(table.seats[0].hands.len(), 3);
+                    assert_eq!(table.seats[0].bet, 300);
+                } else {
+                    assert_eq!(table.split(), Err(BlackJackError::SplitNotAllowed));
+                    assert_eq!(table.seats[0].hands.len(), 2);
+                }
+            }
+        }
+
+        #[test]
+        fn split_aces_take_one_card_each() {
+            let hands = [[Rank::Ace, Rank::Ace], [Rank::Ten, Rank::Nine]];
+            let mut table = dealt_table(rules(), Rank::Seven, &hands);
+            table.split().unwrap();
+            table.give_next(card(Rank::King));
+            table.give_next(card(Rank::Nine));
+            let split = &table.seats[0].hands;
+            assert_eq!((split[0].total, split[1].total), (21, 20));
+            assert!(!split[0].is_natural());
+            assert_eq!(table.active_seat, 1);
+            // without the rule, split aces are played like any hand
+            let rules = TableRules {
+                split_aces_one_card: false,
+                ..rules()
+            };
+            let mut table = dealt_table(rules, Rank::Seven, &hands);
+            table.split().unwrap();
+            table.give_next(card(Rank::King));
+            table.give_next(card(Rank::Nine));
+            assert_eq!(table.active_seat, 0);
+            assert_eq!(table.seats[0].active_hand, 0);
+        }
+
+        #[test]
+        fn every_seat_decides_on_the_insurance_before_the_peek() {
+            let hands = [[Rank::Ten, Rank::Nine], [Rank::Ten, Rank::Seven]];
+            let mut table = dealt_table(rules(), Rank::Ace, &hands);
+            assert_eq!(table.phase, Phase::Insurance);
+            assert_eq!(table.active_seat, 0);
+            assert_eq!(table.insure(50, false), Ok(50));
+            assert_eq!(table.active_seat, 1);
+            assert_eq!(table.phase, Phase::Insurance);
+            assert_eq!(table.insure(0, false), Ok(0));
+            // oracle draws the hole card at the peek
+            assert_eq!(table.phase, Phase::Peek);
+            assert_eq!(table.pending_draws, vec![Hand::Hole]);
+            assert_eq!(table.seats[0].insurance, 50);
+        }
+
+        #[test]
+        fn insurance_is_refused_over_half_the_bet_or_under_a_ten() {
+            let mut table = dealt_table(rules(), Rank::Ace, &[[Rank::Ten, Rank::Nine]]);
+            assert_eq!(table.insure(51, false), Err(BlackJackError::InvalidInsurance));
+            // even money is taken only on a natural
+            assert_eq!(table.insure(0, true), Err(BlackJackError::InvalidInsurance));
+            let rules = TableRules {
+                surrender: SurrenderRule::Early,
+                ..rules()
+            };
+            let mut table = dealt_table(rules, Rank::King, &[[Rank::Ten, Rank::Nine]]);
+            assert_eq!(table.phase, Phase::Insurance);
+            assert_eq!(table.insure(10, false), Err(BlackJackError::InvalidInsurance));
+            assert_eq!(table.insure(0, false), Ok(0));
+        }
+
+        #[test]
+        fn insurance_is_paid_when_the_peek_reveals_a_natural() {
+            let hands = [[Rank::Ten, Rank::Nine], [Rank::Ace, Rank::King]];
+            let mut table = dealt_table(rules(), Rank::Ace, &hands);
+            table.insure(50, false).unwrap();
+            table.insure(0, true).unwrap();
+            table.give_next(card(Rank::Queen));
+            assert_eq!(table.phase, Phase::Settled);
+            assert!(table.dealer_natural());
+            assert_eq!(table.seats[0].hands[0].outcome, Some(Outcome::DealerWin));
+            assert_eq!(table.seats[0].payouts(true), (150, 800));
+            // even money pays the natural 1:1 against the dealer's natural
+            assert_eq!(table.seats[1].hands[0].outcome, Some(Outcome::PlayerWin));
+            assert_eq!(table.seats[1].payouts(true), (200, 700));
+        }
+
+        #[test]
+        fn late_surrender_is_taken_after_the_peek() {
+            let hands = [[Rank::Ten, Rank::Six], [Rank::Ten, Rank::Seven]];
+            let mut table = dealt_table(rules(), Rank::Ace, &hands);
+            assert_eq!(table.surrender(), Err(BlackJackError::SurrenderNotAllowed));
+            table.insure(0, false).unwrap();
+            table.insure(0, false).unwrap();
+            // oracle's hole card makes no natural, so it stays face down
+            table.pending_draws.remove(0);
+            table.dealer_peek(false);
+            assert_eq!(table.phase, Phase::PlayerTurn);
+            assert_eq!(table.surrender(), Ok(()));
+            assert_eq!(table.seats[0].hands[0].outcome, Some(Outcome::Surrender));
+            assert_eq!(table.last_operation, PLAYER_SURRENDER);
+            assert_eq!(table.active_seat, 1);
+        }
+
+        #[test]
+        fn early_surrender_is_taken_against_the_dealer_natural() {
+            let rules = TableRules {
+                surrender: SurrenderRule::Early,
+                ..rules()
+            };
+            let mut table = dealt_table(rules, Rank::King, &[[Rank::Ten, Rank::Six]]);
+            assert_eq!(table.phase, Phase::Insurance);
+            table.surrender().unwrap();
+            assert_eq!(table.pending_draws, vec![Hand::Hole]);
+            table.give_next(card(Rank::Ace));
+            assert_eq!(table.phase, Phase::Settled);
+            assert_eq!(table.seats[0].hands[0].outcome, Some(Outcome::Surrender));
+            assert_eq!(table.seats[0].payouts(true), (50, 850));
+        }
+
+        #[test]
+        fn surrender_is_only_the_first_decision_allowed_by_the_rules() {
+            let disabled = TableRules {
+                surrender: SurrenderRule::Disabled,
+                ..rules()
+            };
+            let mut table = dealt_table(disabled, Rank::Seven, &[[Rank::Ten, Rank::Six]]);
+            assert_eq!(table.surrender(), Err(BlackJackError::SurrenderNotAllowed));
+            let mut table = dealt_table(rules(), Rank::Seven, &[[Rank::Two, Rank::Six]]);
+            table.request_cards(PLAYER_HIT, &[Hand::Player(0, 0)]);
+            table.give_next(card(Rank::Three));
+            assert_eq!(table.surrender(), Err(BlackJackError::SurrenderNotAllowed));
+            let mut table = dealt_table(rules(), Rank::Seven, &[[Rank::Six; 2]]);
+            table.split().unwrap();
+            table.give_next(card(Rank::Two));
+            table.give_next(card(Rank::Three));
+            assert_eq!(table.surrender(), Err(BlackJackError::SurrenderNotAllowed));
+        }
+
+        #[test]
+        fn dealer_peeks_only_under_an_ace_or_a_ten() {
+            let hands = [[Rank::Ten, Rank::Seven]];
+            let table = dealt_from(DeckSource::Committed, rules(), Rank::Nine, &hands);
+            assert_eq!(table.phase, Phase::PlayerTurn);
+            let table = dealt_from(DeckSource::Committed, rules(), Rank::Queen, &hands);
+            assert_eq!(table.phase, Phase::Peek);
+            assert!(table.pending_draws.is_empty());
+        }
+
+        #[test]
+        fn natural_revealed_at_the_peek_settles_the_round() {
+            let hands = [[Rank::Ten, Rank::Seven], [Rank::Ace, Rank::Jack]];
+            let mut table = dealt_from(DeckSource::Committed, rules(), Rank::Queen, &hands);
+            table.dealer_peek(true);
+            assert_eq!(table.pending_draws, vec![Hand::Hole]);
+            table.give_next(card(Rank::Ace));
+            assert_eq!(table.phase, Phase::Settled);
+            assert_eq!(table.last_operation, DEALER_PEEK);
+            assert_eq!(table.seats[0].hands[0].outcome, Some(Outcome::DealerWin));
+            assert_eq!(table.seats[1].hands[0].outcome, Some(Outcome::Push));
+        }
+
+        #[test]
+        fn natural_denied_at_the_peek_forfeits_the_dealer_hand() {
+            let hands = [[Rank::Ten, Rank::Seven]];
+            let mut table = dealt_from(DeckSource::Committed, rules(), Rank::Queen, &hands);
+            table.dealer_peek(false);
+            assert_eq!(table.phase, Phase::PlayerTurn);
+            assert!(table.hole_card.is_some());
+            table.finish_hand();
+            assert_eq!(table.phase, Phase::DealerTurn);
+            table.play_dealer();
+            table.give_next(card(Rank::Ace));
+            assert!(table.dealer_forfeits());
+            assert_eq!(table.last_operation, DEALER_BUSTED);
+            assert_eq!(table.seats[0].hands[0].outcome, Some(Outcome::PlayerWin));
+        }
+
+        #[test]
+        fn round_out_of_cards_resumes_with_the_new_shoe() {
+            let mut table = dealt_table(rules(), Rank::Seven, &[[Rank::Two, Rank::Three]]);
+            let hole = table.hole_card;
+            assert!(hole.is_some());
+            // every other card of the shoe is dealt
+            table.current_card = table.shoe_cards as usize;
+            table.request_cards(PLAYER_HIT, &[Hand::Player(0, 0)]);
+            assert_eq!(table.interrupted, Some(Phase::PlayerTurn));
+            assert_eq!(table.phase, Phase::WaitingForDeck);
+            assert_eq!(table.last_operation, REQUEST_NEW_DECK);
+            assert!(table.needs_shoe());
+            table.new_shoe(DeckSource::Oracle, REQUEST_ORACLE_DECK);
+            // hole card of the previous shoe is dealt again, and can't make a natural
+            assert_eq!(table.pending_draws, vec![Hand::Hole, Hand::Player(0, 0)]);
+            assert_eq!(table.natural, Natural::Excluded);
+            assert_eq!(table.seats[0].hands[0].total, 5);
+            table.resume();
+            assert_eq!(table.phase, Phase::PlayerTurn);
+            assert_eq!(table.interrupted, None);
+            assert_eq!(table.hole_card, Some(0));
+            table.give_next(card(Rank::Four));
+            assert_eq!(table.seats[0].hands[0].total, 9);
+            assert_eq!(table.last_operation, PLAYER_HIT);
+            assert_eq!(table.current_card, 2);
+        }
+
+        #[test]
+        fn oracle_shoe_without_a_card_for_the_denied_hole_has_nothing_to_draw() {
+            let mut table = dealt_table(rules(), Rank::Ace, &[[Rank::Two, Rank::Three]]);
+            table.natural = Natural::Denied;
+            table.hole_card = Some(0);
+            // every card left in the shoe makes a natural with the ace
+            table.dealt_cards = (0..CARD_NUMBER as usize)
+                .map(|i| (card_at(i).rank.value() != 10) as u8)
+                .collect();
+            table.request_cards(PLAYER_HIT, &[Hand::Player(0, 0)]);
+            assert!(table.oracle_weights().iter().all(|&w| w == 0));
+        }
+
+        #[test]
+        fn shoe_dealt_to_its_cut_card_needs_a_new_one() {
+            let mut table = dealt_table(rules(), Rank::Seven, &[[Rank::Two, Rank::Three]]);
+            table.settle();
+            assert!(!table.needs_shoe());
+            table.current_card = table.cut_card as usize;
+            assert!(table.needs_shoe());
+            // cut card of the current shoe holds until the next shoe
+            table.rules.cut_card = 52;
+            assert!(table.needs_shoe());
+            table.new_shoe(DeckSource::Oracle, REQUEST_ORACLE_DECK);
+            assert_eq!(table.cut_card, 52);
+            assert!(table.seats[0].hands.is_empty());
+        }
+
+        #[test]
+        fn round_waits_for_the_dealer_to_reveal_its_cards_and_open_its_ended_shoe() {
+            let hands = [[Rank::Two, Rank::Three]];
+            let mut table = dealt_from(DeckSource::Committed, rules(), Rank::Seven, &hands);
+            table.deck_root = [1; 32];
+            assert!(!table.waits_for_dealer());
+            table.request_cards(PLAYER_HIT, &[Hand::Player(0, 0)]);
+            assert!(table.waits_for_dealer());
+            table.give_next(card(Rank::Four));
+            assert!(!table.waits_for_dealer());
+            table.settle();
+            assert!(!table.waits_for_dealer());
+            table.current_card = table.cut_card as usize;
+            assert!(table.waits_for_dealer());
+            table.forfeit_round();
+            assert!(!table.waits_for_dealer());
+            assert_eq!(table.seats[0].hands[0].outcome, Some(Outcome::Forfeit));
+            // anyone draws the cards of the oracle's shoe
+            let mut table = dealt_table(rules(), Rank::Seven, &hands);
+            table.request_cards(PLAYER_HIT, &[Hand::Player(0, 0)]);
+            assert!(!table.waits_for_dealer());
+        }
+
+        #[test]
+        fn seats_which_did_not_reveal_their_seeds_are_dropped_from_the_shuffle() {
+            let mut table = new_table(rules());
+            table.new_shoe(DeckSource::Committed, SEND_DECK);
+            for seed in [Some([1; 32]), None] {
+                let mut seat = Seat::new(Pubkey::new_unique());
+                seat.bet = 100;
+                seat.seed_commitment = Some([0; 32]);
+                seat.seed = seed;
+                table.seats.push(seat);
+            }
+            table.dealer_seed = Some([2; 32]);
+            table.derive_order();
+            assert!(!table.shuffled());
+            table.drop_seeds();
+            assert_eq!(table.deck_order.len(), 52);
+            assert_eq!(table.last_operation, DROP_SEEDS);
+            assert_eq!(table.seats[1].seed_commitment, None);
+            assert!(table.dealt_in(&table.seats[0]));
+            assert!(!table.dealt_in(&table.seats[1]));
+        }
+
+        // CARDS of a committed shoe, each with the salt of its position, and the root
+        // of the commitment to them.
+        fn committed_cards(cards: Vec<Card>) -> (Vec<OpenedCard>, [u8; 32]) {
+            let opened: Vec<OpenedCard> = cards
+                .into_iter()
+                .enumerate()
+                .map(|(position, card)| OpenedCard {
+                    card,
+                    salt: [position as u8; 32],
+                })
+                .collect();
+            let mut subtrees = Vec::new();
+            for (position, o) in opened.iter().enumerate() {
+                push_leaf(&mut subtrees, position, leaf_hash(position, &o.card, &o.salt));
+            }
+            (opened, subtrees_root(&subtrees).unwrap())
+        }
+
+        // Settled round dealt from the committed shoe with ROOT, which is dealt to its cut card.
+        fn ended_shoe(root: [u8; 32]) -> BlackJackAccountData {
+            let mut table = dealt_from(
+                DeckSource::Committed,
+                rules(),
+                Rank::Seven,
+                &[[Rank::Two, Rank::Three]],
+            );
+            table.settle();
+            table.deck_root = root;
+            table.current_card = table.cut_card as usize;
+            table
+        }
+
+        #[test]
+        fn ended_shoe_is_opened_in_batches_against_its_commitment() {
+            let (cards, root) = committed_cards((0..52).map(card_at).collect());
+            let mut table = ended_shoe(root);
+            assert!(table.shoe_unopened());
+            assert_eq!(table.open_shoe(&cards[..30]), Ok(()));
+            assert_eq!(table.opened, 30);
+            assert!(table.shoe_unopened());
+            assert_eq!(table.open_shoe(&cards[..23]), Err(BlackJackError::InvalidDeck));
+            assert_eq!(table.open_shoe(&cards[30..]), Ok(()));
+            assert!(!table.shoe_unopened());
+            assert!(!table.forfeited);
+            assert_eq!(table.last_operation, OPEN_SHOE);
+            assert_eq!(table.seats[0].hands[0].outcome, Some(Outcome::DealerWin));
+        }
+
+        #[test]
+        fn stacked_shoe_forfeits_the_round_to_the_players() {
+            // ace of spades is dealt twice, and the two of spades never
+            let mut stacked: Vec<Card> = (0..52).map(card_at).collect();
+            stacked[1] = stacked[0];
+            let (cards, root) = committed_cards(stacked);
+            let mut table = ended_shoe(root);
+            assert_eq!(table.open_shoe(&cards), Ok(()));
+            assert!(table.forfeited);
+            assert_eq!(table.last_operation, SHOE_FORFEIT);
+            assert_eq!(table.seats[0].hands[0].outcome, Some(Outcome::Forfeit));
+            let escrow = 100 + worst_case_payout(100).unwrap();
+            assert_eq!(table.seats[0].payouts(false), (escrow, 0));
+            assert!(table.needs_shoe());
+            assert!(!table.shoe_unopened());
+        }
+
+        #[test]
+        fn shoe_opened_against_another_commitment_forfeits_the_round() {
+            let (cards, _) = committed_cards((0..52).map(card_at).collect());
+            let mut table = ended_shoe([1; 32]);
+            assert_eq!(table.open_shoe(&cards), Ok(()));
+            assert!(table.forfeited);
+            assert_eq!(table.seats[0].hands[0].outcome, Some(Outcome::Forfeit));
+        }
+    }
@@ -1,4 +1,10 @@
+pub mod error;
+pub mod house;
 pub mod instructions;
+pub mod oracle;
+pub mod pda;
+pub mod shuffle;
+pub mod token;
 
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
@@ -6,6 +12,9 @@ use solana_program::{
     program_error::ProgramError,
     pubkey::Pubkey,
 };
+use black_jack_core::constants::*;
+use black_jack_core::state::BlackJackAccountData;
+use crate::error::BlackJackError;
 use crate::instructions::*;
 
 // Declare the programs entrypoint. The entrypoint is the function
@@ -15,18 +24,33 @@ entrypoint!(process_instruction);
 
 /// Logic that runs when the program is executed.
 ///
-/// The account passed in ought to contain a `BlackJackAccountData`.
+/// The account passed in ought to contain a `BlackJackAccountData`, at the address derived
+/// from the dealer and the index of the table. Second account must sign the instruction,
+/// as the dealer or a player of the table, depending on the instruction.
+/// Only `CREATE_TABLE` takes a table account which the program does not own yet,
+/// as the program creates the account, with the dealer as the signer.
 pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> entrypoint::ProgramResult {
-    msg!("instruction data: {:?}", instruction_data);
+    let instruction = BlackJackInstruction::unpack(instruction_data)?;
+    msg!("instruction: {}", instruction.name());
+    let operation = instruction.operation();
 
     // Get the account that stores greeting count information.
     let accounts_iter = &mut accounts.iter();
     let account = next_account_info(accounts_iter)?;
 
+    if let BlackJackInstruction::CreateTable(create_table) = instruction {
+        let dealer = next_account_info(accounts_iter)?;
+        if !dealer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let system_program = next_account_info(accounts_iter)?;
+        return unpack_create_table(create_table, program_id, account, dealer, system_program);
+    }
+
     // The account must be owned by the program in order for the
     // program to write to it. If that is not the case then the
     // program has been invoked incorrectly and we report as much.
@@ -34,37 +58,148 @@ pub fn process_instruction(
         return Err(ProgramError::IncorrectProgramId);
     }
     msg!("account data len: {}", account.data_len());
-    msg!("account data: {:?}", &account.data.borrow());
 
-    match instruction_data[0] {
-        SEND_DECK => {
-            unpack_send_deck(&instruction_data[1..], account);
+    // Dealer is the wallet which created the table. Players are the wallets seated
+    // at the table.
+    let signer = next_account_info(accounts_iter)?;
+    if !signer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    // Table is decoded once for the checks of the signer, the phase and the turn.
+    let table = match BlackJackAccountData::unpack(&account.data.borrow()) {
+        Ok(table) => table,
+        Err(_) => return Err(BlackJackError::AccountSerializationError.into()),
+    };
+    let is_dealer = table.dealer == *signer.key;
+    let is_player = table.seat_of(signer.key).is_some();
+    let authorized = match operation {
+        PLAYER_COMMIT_SEED | PLAYER_REVEAL_SEED | PLAYER_HIT | PLAYER_STAND | PLAYER_DOUBLE
+        | PLAYER_SPLIT | INSURANCE | PLAYER_SURRENDER | PLACE_BET | WITHDRAW_BET
+        | CLAIM_FORFEIT => is_player,
+        // dealer can't take a seat, and contribute a player's seed to its own shoe
+        JOIN_TABLE => !is_dealer,
+        CLEAR_DATA | PAYOUT | DEALER_PLAY | DRAW_ORACLE_CARD | DROP_SEEDS => is_dealer || is_player,
+        _ => is_dealer,
+    };
+    if !authorized {
+        return Err(BlackJackError::NotAuthorized.into());
+    }
+    check_phase(&table, operation)?;
+    check_turn(&table, operation, signer.key)?;
+
+    match instruction {
+        BlackJackInstruction::SendDeck(send_deck) => {
+            unpack_send_deck(send_deck, account)
         }
-        DEAL => {
-            unpack_deal(account);
+        BlackJackInstruction::Deal => {
+            let house = next_account_info(accounts_iter)?;
+            unpack_deal(program_id, account, house, accounts_iter.as_slice())
         }
-        CLEAR_DATA => {
-            unpack_clear_data(account);
+        BlackJackInstruction::RevealCard(revealed) => unpack_reveal_card(revealed, account),
+        BlackJackInstruction::RequestOracleDeck => {
+            let randomness = next_account_info(accounts_iter)?;
+            let oracle = next_account_info(accounts_iter)?;
+            let system_program = next_account_info(accounts_iter)?;
+            unpack_request_oracle_deck(
+                program_id,
+                account,
+                signer,
+                randomness,
+                oracle,
+                system_program,
+            )
         }
-        DEALER_HIT => {
-            unpack_hit(account, DEALER_HIT);
+        BlackJackInstruction::DrawOracleCard => {
+            let randomness = next_account_info(accounts_iter)?;
+            let oracle = next_account_info(accounts_iter)?;
+            unpack_draw_oracle_card(program_id, account, randomness, oracle)
         }
-        PLAYER_HIT => {
-            unpack_hit(account, PLAYER_HIT);
+        BlackJackInstruction::JoinTable => unpack_join_table(account, signer.key),
+        BlackJackInstruction::PlayerCommitSeed(commit_seed) => {
+            unpack_commit_seed(commit_seed, account, signer.key)
         }
-        DEALER_STAND => {
-            unpack_stand(account, DEALER_STAND);
+        BlackJackInstruction::PlayerRevealSeed(reveal_seed) => {
+            unpack_reveal_seed(reveal_seed, account, PLAYER_REVEAL_SEED, signer.key)
         }
-        PLAYER_STAND => {
-            unpack_stand(account, PLAYER_STAND);
+        BlackJackInstruction::DealerRevealSeed(reveal_seed) => {
+            unpack_reveal_seed(reveal_seed, account, DEALER_REVEAL_SEED, signer.key)
         }
-        PLAYER_BUSTED => {
-            unpack_busted(account, PLAYER_BUSTED);
+        BlackJackInstruction::ClearData => unpack_clear_data(account, signer.key),
+        BlackJackInstruction::PlayerHit => unpack_hit(account),
+        BlackJackInstruction::PlayerStand => unpack_stand(account),
+        BlackJackInstruction::PlayerDouble => {
+            let system_program = next_account_info(accounts_iter)?;
+            unpack_double(account, signer, system_program, accounts_iter.as_slice())
         }
-        DEALER_BUSTED => {
-            unpack_busted(account, DEALER_BUSTED);
+        BlackJackInstruction::Insurance(insurance) => {
+            let system_program = next_account_info(accounts_iter)?;
+            unpack_insurance(
+                insurance,
+                account,
+                signer,
+                system_program,
+                accounts_iter.as_slice(),
+            )
         }
-        _ => (),
-    }
-    Ok(())
+        BlackJackInstruction::PlayerSurrender => unpack_surrender(account),
+        BlackJackInstruction::PlayerSplit => {
+            let system_program = next_account_info(accounts_iter)?;
+            unpack_split(account, signer, system_program, accounts_iter.as_slice())
+        }
+        BlackJackInstruction::DealerPlay => unpack_dealer_play(account),
+        BlackJackInstruction::DealerPeek(natural) => unpack_dealer_peek(natural, account),
+        BlackJackInstruction::OpenShoe(cards) => unpack_open_shoe(cards, account),
+        BlackJackInstruction::DropSeeds => unpack_drop_seeds(account),
+        BlackJackInstruction::ClaimForfeit => unpack_claim_forfeit(account),
+        BlackJackInstruction::WithdrawBet => {
+            unpack_withdraw_bet(account, signer, accounts_iter.as_slice())
+        }
+        BlackJackInstruction::SetRules(rules) => unpack_set_rules(rules, account),
+        BlackJackInstruction::SetMint(mint) => unpack_set_mint(mint, account),
+        BlackJackInstruction::PlaceBet(place_bet) => {
+            let system_program = next_account_info(accounts_iter)?;
+            let house = next_account_info(accounts_iter)?;
+            unpack_place_bet(
+                place_bet,
+                program_id,
+                account,
+                signer,
+                system_program,
+                house,
+                accounts_iter.as_slice(),
+            )
+        }
+        BlackJackInstruction::Payout => {
+            let house = next_account_info(accounts_iter)?;
+            unpack_payout(program_id, account, house, accounts_iter.as_slice())
+        }
+        BlackJackInstruction::HouseDeposit(transfer) => {
+            let house = next_account_info(accounts_iter)?;
+            let system_program = next_account_info(accounts_iter)?;
+            unpack_house_deposit(
+                transfer,
+                program_id,
+                account,
+                signer,
+                house,
+                system_program,
+                accounts_iter.as_slice(),
+            )
+        }
+        BlackJackInstruction::HouseWithdraw(transfer) => {
+            let house = next_account_info(accounts_iter)?;
+            unpack_house_withdraw(
+                transfer,
+                program_id,
+                account,
+                signer,
+                house,
+                accounts_iter.as_slice(),
+            )
+        }
+        // Handled before the owner check, as the program does not own the account yet.
+        BlackJackInstruction::CreateTable(_) => Err(BlackJackError::InvalidInstruction.into()),
+    }?;
+    // dealer which stalls the dealt round loses it to the players
+    restart_dealer_deadline(&table, account, is_dealer)
 }
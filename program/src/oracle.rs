@@ -0,0 +1,65 @@
+use borsh::BorshDeserialize;
+use mock_oracle::Randomness;
+use solana_program::account_info::AccountInfo;
+use solana_program::program::invoke_signed;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+use black_jack_core::oracle::{randomness_address, ORACLE_SEED};
+
+use crate::error::BlackJackError;
+
+// Oracle's shoe. Every card is drawn with a fresh random value, requested from the oracle
+// through cross-program invocation into the randomness account of the table. No order of
+// the shoe is stored, so no card is known before it is drawn.
+//
+// Random value is fulfilled synchronously, within the instruction which draws the card, and
+// the mock oracle derives it from its previous value and the clock. Anyone who reads the
+// randomness account can compute the next card before drawing it, and pick when to hit.
+// Only the program signs for the randomness account of the table, so the value advances
+// with the drawn cards alone, and can't be requested again until the next card suits.
+// Oracle shoe is for local testing only, a real oracle must fulfill the request in a later
+// transaction, with a value not known when the request is made.
+
+/// Check that ORACLE is the known oracle, and RANDOMNESS the randomness account of the TABLE
+/// account. Returns its bump seed.
+pub fn check_randomness(
+    randomness: &AccountInfo,
+    oracle: &AccountInfo,
+    table: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<u8, ProgramError> {
+    if *oracle.key != mock_oracle::id() {
+        return Err(BlackJackError::UnknownOracle.into());
+    }
+    let (expected, bump) = randomness_address(table, program_id);
+    if *randomness.key != expected {
+        return Err(BlackJackError::InvalidRandomnessAccount.into());
+    }
+    Ok(bump)
+}
+
+/// Fresh random number, written by the ORACLE into the RANDOMNESS account of the TABLE
+/// on request. Program signs the request for the randomness account, with its BUMP seed.
+pub fn request_random<'a>(
+    randomness: &AccountInfo<'a>,
+    oracle: &AccountInfo<'a>,
+    table: &Pubkey,
+    bump: u8,
+) -> Result<u64, ProgramError> {
+    if randomness.owner != oracle.key {
+        return Err(BlackJackError::InvalidRandomnessAccount.into());
+    }
+    invoke_signed(
+        &mock_oracle::request_randomness(randomness.key),
+        &[randomness.clone(), oracle.clone()],
+        &[&[ORACLE_SEED, table.as_ref(), &[bump]]],
+    )?;
+    let value = match Randomness::deserialize(&mut &randomness.data.borrow()[..]) {
+        Ok(r) => r.value,
+        Err(_) => return Err(BlackJackError::InvalidRandomnessAccount.into()),
+    };
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&value[..8]);
+    Ok(u64::from_le_bytes(bytes))
+}
@@ -0,0 +1,44 @@
+use solana_program::hash::hashv;
+
+// Order in which the deck positions of the committed deck are dealt. It is derived from
+// the dealer's seed and the seeds of the seats, so nobody alone controls the order of
+// the cards. Cards of the oracle's shoe are drawn one by one, with the chances of the cards
+// left in the shoe.
+
+/// Permutation of LEN deck positions, derived from the dealer's seed and the PLAYER_SEEDS
+/// in the order of the seats, with Fisher-Yates shuffle.
+pub fn deck_order(dealer_seed: &[u8; 32], player_seeds: &[[u8; 32]], len: usize) -> Vec<u16> {
+    let mut seeds: Vec<&[u8]> = vec![b"shuffle", dealer_seed];
+    seeds.extend(player_seeds.iter().map(|s| s.as_slice()));
+    shuffle(hashv(&seeds).as_ref(), len)
+}
+
+/// Index picked by RANDOM, with the chance of every index proportional to its WEIGHTS.
+/// None if all weights are zero.
+pub fn pick(random: u64, weights: &[u64]) -> Option<usize> {
+    let total: u64 = weights.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let mut target = random % total;
+    for (i, &weight) in weights.iter().enumerate() {
+        if target < weight {
+            return Some(i);
+        }
+        target -= weight;
+    }
+    None
+}
+
+/// Fisher-Yates shuffle of LEN deck positions, with random numbers derived from SEED.
+fn shuffle(seed: &[u8], len: usize) -> Vec<u16> {
+    let mut order: Vec<u16> = (0..len as u16).collect();
+    for i in (1..len).rev() {
+        let random = hashv(&[seed, &(i as u32).to_le_bytes()]).to_bytes();
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&random[..8]);
+        let j = (u64::from_le_bytes(bytes) % (i as u64 + 1)) as usize;
+        order.swap(i, j);
+    }
+    order
+}
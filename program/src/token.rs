@@ -0,0 +1,109 @@
+use solana_program::account_info::AccountInfo;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program::{invoke, invoke_signed};
+use solana_program::program_error::ProgramError;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use spl_token::state::Account as TokenAccount;
+
+use crate::error::BlackJackError;
+
+// Token escrow. When the table is configured with a mint, stakes are held in
+// a token account of the table. The table is an address derived by the program,
+// so only the program can move tokens out of the vault, signing for the table.
+
+/// Check that TOKEN_ACCOUNT holds tokens of MINT, and belongs to OWNER.
+pub fn check_token_account(
+    token_account: &AccountInfo,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> ProgramResult {
+    if *token_account.owner != spl_token::id() {
+        return Err(BlackJackError::InvalidTokenAccount.into());
+    }
+    let account = match TokenAccount::unpack(&token_account.data.borrow()) {
+        Ok(acc) => acc,
+        Err(_) => return Err(BlackJackError::InvalidTokenAccount.into()),
+    };
+    if account.owner != *owner || account.mint != *mint {
+        return Err(BlackJackError::InvalidTokenAccount.into());
+    }
+    Ok(())
+}
+
+/// Check that VAULT is the token account of the TABLE, for MINT.
+pub fn check_vault(vault: &AccountInfo, table: &Pubkey, mint: &Pubkey) -> ProgramResult {
+    check_token_account(vault, table, mint)
+}
+
+/// Move AMOUNT of tokens from the account of OWNER, who signs the instruction, into VAULT.
+pub fn deposit<'a>(
+    from: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    if *token_program.key != spl_token::id() {
+        return Err(BlackJackError::InvalidTokenAccount.into());
+    }
+    invoke(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            from.key,
+            vault.key,
+            owner.key,
+            &[],
+            amount,
+        )?,
+        &[
+            from.clone(),
+            vault.clone(),
+            owner.clone(),
+            token_program.clone(),
+        ],
+    )
+}
+
+/// Move AMOUNT of tokens from the account FROM to the account TO. Transfer is signed
+/// by the program, for the AUTHORITY of FROM derived from SEEDS.
+pub fn transfer_signed<'a>(
+    from: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    amount: u64,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    if *token_program.key != spl_token::id() {
+        return Err(BlackJackError::InvalidTokenAccount.into());
+    }
+    if amount == 0 {
+        return Ok(());
+    }
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            from.key,
+            to.key,
+            authority.key,
+            &[],
+            amount,
+        )?,
+        &[
+            from.clone(),
+            to.clone(),
+            authority.clone(),
+            token_program.clone(),
+        ],
+        &[seeds],
+    )
+}
+
+/// Tokens held by TOKEN_ACCOUNT.
+pub fn token_balance(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    match TokenAccount::unpack(&token_account.data.borrow()) {
+        Ok(acc) => Ok(acc.amount),
+        Err(_) => Err(BlackJackError::InvalidTokenAccount.into()),
+    }
+}
@@ -0,0 +1,237 @@
+use black_jack::instructions::{BlackJackInstruction, CreateTable};
+use black_jack_core::card::{card_at, Rank};
+use black_jack_core::constants::{AWAITING_REVEAL, CARD_NUMBER};
+use black_jack_core::house::house_address;
+use black_jack_core::oracle::randomness_address;
+use black_jack_core::state::{BlackJackAccountData, Phase};
+use borsh::{BorshDeserialize, BorshSerialize};
+use mock_oracle::Randomness;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::AccountSharedData;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::system_program;
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+// Oracle's shoe through the cross-program invocation of the mock oracle: the randomness
+// account of the table, and one request of randomness for every drawn card.
+
+async fn start() -> (ProgramTestContext, Pubkey) {
+    let program_id = Pubkey::new_unique();
+    let mut test = ProgramTest::new(
+        "black_jack",
+        program_id,
+        processor!(black_jack::process_instruction),
+    );
+    test.add_program(
+        "mock_oracle",
+        mock_oracle::id(),
+        processor!(mock_oracle::process_instruction),
+    );
+    (test.start_with_context().await, program_id)
+}
+
+async fn send(
+    context: &mut ProgramTestContext,
+    program_id: &Pubkey,
+    instruction: BlackJackInstruction,
+    accounts: Vec<AccountMeta>,
+) -> Result<(), BanksClientError> {
+    let dealer = context.payer.pubkey();
+    let table = BlackJackAccountData::address(&dealer, 0, program_id).0;
+    let instruction = instruction
+        .instruction(program_id, &table, &dealer, accounts)
+        .unwrap();
+    // every transaction of the same instruction needs a fresh blockhash
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&dealer),
+        &[&context.payer],
+        blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn table(context: &mut ProgramTestContext, program_id: &Pubkey) -> BlackJackAccountData {
+    let table = BlackJackAccountData::address(&context.payer.pubkey(), 0, program_id).0;
+    let account = context
+        .banks_client
+        .get_account(table)
+        .await
+        .unwrap()
+        .unwrap();
+    BlackJackAccountData::unpack(&account.data).unwrap()
+}
+
+async fn create_table(context: &mut ProgramTestContext, program_id: &Pubkey) -> Pubkey {
+    let create = BlackJackInstruction::CreateTable(CreateTable { index: 0, decks: 1 });
+    let accounts = vec![AccountMeta::new_readonly(system_program::id(), false)];
+    send(context, program_id, create, accounts).await.unwrap();
+    BlackJackAccountData::address(&context.payer.pubkey(), 0, program_id).0
+}
+
+fn oracle_accounts(randomness: Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(randomness, false),
+        AccountMeta::new_readonly(mock_oracle::id(), false),
+    ]
+}
+
+#[tokio::test]
+async fn oracle_draws_every_card_with_its_own_randomness() {
+    let (mut context, program_id) = start().await;
+    let table_address = create_table(&mut context, &program_id).await;
+    let randomness = randomness_address(&table_address, &program_id).0;
+
+    let mut accounts = oracle_accounts(randomness);
+    accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    send(
+        &mut context,
+        &program_id,
+        BlackJackInstruction::RequestOracleDeck,
+        accounts,
+    )
+    .await
+    .unwrap();
+    let account = context
+        .banks_client
+        .get_account(randomness)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, mock_oracle::id());
+
+    // aces and tens are taken out of the shoe, so the dealer never peeks
+    let mut account = context
+        .banks_client
+        .get_account(table_address)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut data = BlackJackAccountData::unpack(&account.data).unwrap();
+    let taken_out: Vec<usize> = (0..CARD_NUMBER as usize)
+        .filter(|&i| card_at(i).rank == Rank::Ace || card_at(i).rank.value() == 10)
+        .collect();
+    for &i in &taken_out {
+        data.dealt_cards[i] = 1;
+    }
+    data.serialize(&mut &mut account.data[..]).unwrap();
+    context.set_account(&table_address, &AccountSharedData::from(account));
+
+    let house = house_address(&table_address, &program_id).0;
+    let accounts = vec![AccountMeta::new(house, false)];
+    send(
+        &mut context,
+        &program_id,
+        BlackJackInstruction::Deal,
+        accounts,
+    )
+    .await
+    .unwrap();
+
+    // table without seats: the dealer's up card, and the hole card revealed when the round
+    // is settled
+    let mut draws = 0;
+    loop {
+        let data = table(&mut context, &program_id).await;
+        assert!(data.deck_order.is_empty());
+        if data.last_operation != AWAITING_REVEAL || data.pending_draws.is_empty() {
+            break;
+        }
+        let accounts = oracle_accounts(randomness);
+        send(
+            &mut context,
+            &program_id,
+            BlackJackInstruction::DrawOracleCard,
+            accounts,
+        )
+        .await
+        .unwrap();
+        draws += 1;
+    }
+    let data = table(&mut context, &program_id).await;
+    assert_eq!(data.phase, Phase::Settled);
+    assert_eq!(data.dealer_cards.len(), 2);
+    assert_eq!(
+        data.dealt_cards.iter().map(|&c| c as usize).sum::<usize>(),
+        taken_out.len() + 2
+    );
+    assert_eq!(draws, 2);
+
+    let account = context
+        .banks_client
+        .get_account(randomness)
+        .await
+        .unwrap()
+        .unwrap();
+    let randomness = Randomness::deserialize(&mut &account.data[..]).unwrap();
+    assert_eq!(randomness.counter, draws);
+}
+
+#[tokio::test]
+async fn oracle_refuses_randomness_requested_outside_the_program() {
+    let (mut context, program_id) = start().await;
+    let table_address = create_table(&mut context, &program_id).await;
+    let randomness = randomness_address(&table_address, &program_id).0;
+    let mut accounts = oracle_accounts(randomness);
+    accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    send(
+        &mut context,
+        &program_id,
+        BlackJackInstruction::RequestOracleDeck,
+        accounts,
+    )
+    .await
+    .unwrap();
+
+    // only the program signs for the randomness account of the table
+    let request = Instruction::new_with_bytes(
+        mock_oracle::id(),
+        &[mock_oracle::REQUEST_RANDOMNESS],
+        vec![AccountMeta::new(randomness, false)],
+    );
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[request],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        blockhash,
+    );
+    let error = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::MissingRequiredSignature)
+    );
+}
+
+#[tokio::test]
+async fn oracle_deck_refuses_randomness_account_of_another_table() {
+    let (mut context, program_id) = start().await;
+    create_table(&mut context, &program_id).await;
+    let other_table = Pubkey::new_unique();
+    let randomness = randomness_address(&other_table, &program_id).0;
+
+    let mut accounts = oracle_accounts(randomness);
+    accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    let error = send(
+        &mut context,
+        &program_id,
+        BlackJackInstruction::RequestOracleDeck,
+        accounts,
+    )
+    .await
+    .unwrap_err()
+    .unwrap();
+    let invalid_randomness = black_jack::error::BlackJackError::InvalidRandomnessAccount as u32;
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::Custom(invalid_randomness))
+    );
+}
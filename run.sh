@@ -1,10 +1,15 @@
 #!/bin/bash
 
 function build_bpf() {
+	# program id of the mock oracle, generated once for every checkout
+	if [ ! -f oracle/oracle-keypair.json ]; then
+		solana-keygen new --no-bip39-passphrase --silent -o oracle/oracle-keypair.json
+	fi
 	cd clients
 	cargo build
 	cd ..
     cargo build-bpf --manifest-path=program/Cargo.toml --bpf-out-dir=program/dist/program
+    cargo build-bpf --manifest-path=oracle/Cargo.toml --bpf-out-dir=oracle/dist/program
 }
 
 case $1 in
@@ -14,17 +19,26 @@ case $1 in
     "deploy")
 	build_bpf
 	solana program deploy program/dist/program/black_jack.so
+	solana program deploy --program-id oracle/oracle-keypair.json oracle/dist/program/mock_oracle.so
 	;;
     "dealer")
-	(./clients/target/debug/dealer program/dist/program/black_jack-keypair.json)
+	(./clients/target/debug/dealer program/dist/program/black_jack-keypair.json ${@:2})
+	;;
+    "oracle-dealer")
+	(./clients/target/debug/dealer program/dist/program/black_jack-keypair.json oracle/oracle-keypair.json ${@:2})
+	;;
+    "house")
+	(./clients/target/debug/dealer program/dist/program/black_jack-keypair.json house ${@:2})
 	;;
 	"player")
-	(./clients/target/debug/player program/dist/program/black_jack-keypair.json)
+	(cd clients/; ./target/debug/player ../program/dist/program/black_jack-keypair.json ${@:2})
 	;;
     "clean")
 	(cd clients/; cargo clean)
 	(cd program/; cargo clean)
-	rm -rf program/dist/
+	(cd core/; cargo clean)
+	(cd oracle/; cargo clean)
+	rm -rf program/dist/ oracle/dist/
 	;;
     *)
 	echo "usage: $0 [build|clean|client]"